@@ -1,18 +1,244 @@
-use std::collections::HashSet;
+use crate::no_answer::NoAnswer;
+use crate::parse_error::{parse_lines, ParseError};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead};
+
+const EXPECTED: &str = "a frequency change such as +3 or -7";
 
 #[aoc_generator(day1)]
-pub fn input_frequency(input: &str) -> Vec<isize> {
-    input.lines().map(|l| l.parse().unwrap()).collect()
+pub fn input_frequency(input: &str) -> Result<Vec<isize>, ParseError> {
+    parse_lines(input, 1, EXPECTED, |l| l.trim().parse().ok())
+}
+
+#[derive(Debug)]
+pub enum CalibrationError {
+    Io(io::Error),
+    Parse(ParseError),
+    Overflow { line: usize },
+}
+
+impl fmt::Display for CalibrationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CalibrationError::Io(error) => write!(f, "day 1: could not read changes: {}", error),
+            CalibrationError::Parse(error) => write!(f, "{}", error),
+            CalibrationError::Overflow { line } => {
+                write!(f, "day 1, line {}: frequency overflowed", line)
+            }
+        }
+    }
+}
+
+impl Error for CalibrationError {}
+
+// Running statistics over a stream of frequency changes, so that device logs
+// can be fed through without holding them in memory. The frequencies seen are
+// only remembered until the first repeat turns up
+#[derive(Debug, PartialEq, Clone)]
+pub struct Calibration {
+    frequency: i64,
+    min: i64,
+    max: i64,
+    changes: usize,
+    first_repeat: Option<(i64, usize)>,
+    seen: HashSet<i64>,
+}
+
+impl Default for Calibration {
+    fn default() -> Self {
+        Calibration::new()
+    }
+}
+
+impl Calibration {
+    pub fn new() -> Calibration {
+        Calibration {
+            frequency: 0,
+            min: 0,
+            max: 0,
+            changes: 0,
+            first_repeat: None,
+            seen: [0].iter().cloned().collect(),
+        }
+    }
+
+    pub fn push(&mut self, delta: i64) -> Result<(), CalibrationError> {
+        self.frequency = self
+            .frequency
+            .checked_add(delta)
+            .ok_or(CalibrationError::Overflow {
+                line: self.changes + 1,
+            })?;
+        self.changes += 1;
+        self.min = self.min.min(self.frequency);
+        self.max = self.max.max(self.frequency);
+
+        if self.first_repeat.is_none() && !self.seen.insert(self.frequency) {
+            self.first_repeat = Some((self.frequency, self.changes));
+            self.seen = HashSet::new();
+        }
+        Ok(())
+    }
+
+    pub fn from_deltas<I: IntoIterator<Item = i64>>(
+        deltas: I,
+    ) -> Result<Calibration, CalibrationError> {
+        let mut calibration = Calibration::new();
+        for delta in deltas {
+            calibration.push(delta)?;
+        }
+        Ok(calibration)
+    }
+
+    // Reads one change per line, the same format as the puzzle input
+    pub fn from_reader<R: BufRead>(reader: R) -> Result<Calibration, CalibrationError> {
+        let mut calibration = Calibration::new();
+        for (text, line) in reader.lines().zip(1..) {
+            let text = text.map_err(CalibrationError::Io)?;
+            let delta = text
+                .trim()
+                .parse()
+                .map_err(|_| CalibrationError::Parse(ParseError::new(1, line, &text, EXPECTED)))?;
+            calibration.push(delta)?;
+        }
+        Ok(calibration)
+    }
+
+    pub fn frequency(&self) -> i64 {
+        self.frequency
+    }
+
+    // The lowest and highest frequencies reached, counting the starting 0
+    pub fn range(&self) -> (i64, i64) {
+        (self.min, self.max)
+    }
+
+    pub fn changes(&self) -> usize {
+        self.changes
+    }
+
+    // The first frequency reached twice, with the line of the change that
+    // reached it again
+    pub fn first_repeat(&self) -> Option<(i64, usize)> {
+        self.first_repeat
+    }
 }
 
 #[aoc(day1, part1)]
-pub fn part1(input: &[isize]) -> isize {
-    let result = input.iter().sum();
-    result
+pub fn part1(input: &[isize]) -> Result<i64, CalibrationError> {
+    Calibration::from_deltas(input.iter().map(|&delta| delta as i64))
+        .map(|calibration| calibration.frequency())
+}
+
+// Where the running frequency first reaches a value it has seen before.
+// `pass` counts the trips through the list from 1, and `line` is the line of
+// the change that produced the repeat
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Repeat {
+    pub frequency: isize,
+    pub pass: usize,
+    pub line: usize,
+}
+
+// Every pass adds the same drift to the frequencies of the first pass, so two
+// of them can only ever meet if they are equal or a whole number of drifts
+// apart. When no pair is, a brute force search would never end
+fn ever_repeats(input: &[isize]) -> bool {
+    let drift: isize = input.iter().sum();
+    let mut seen = HashSet::new();
+    let mut freq: isize = 0;
+
+    for num in input {
+        let class = if drift == 0 {
+            freq
+        } else {
+            freq.rem_euclid(drift.abs())
+        };
+        if !seen.insert(class) {
+            return true;
+        }
+        freq += num;
+    }
+    drift == 0 && !input.is_empty()
+}
+
+// Numbering every frequency reached from 0 for the starting one, frequency k
+// is the frequency before change k % n of the first pass, plus k / n drifts.
+// A repeat inside the first pass is found directly. Otherwise frequency k can
+// only match an earlier one from the same residue class modulo the drift, and
+// the earliest match is the nearest member of the class further along in the
+// direction of the drift, reached after as many passes as drifts lie between
+pub fn first_repeat(input: &[isize]) -> Result<Repeat, NoAnswer> {
+    let n = input.len();
+    let drift: isize = input.iter().sum();
+    let starts: Vec<isize> = input
+        .iter()
+        .scan(0, |freq, num| {
+            let start = *freq;
+            *freq += num;
+            Some(start)
+        })
+        .collect();
+
+    let repeat_at = |k: usize, frequency: isize| Repeat {
+        frequency,
+        pass: (k - 1) / n + 1,
+        line: (k - 1) % n + 1,
+    };
+
+    let mut seen = HashSet::new();
+    for (k, &freq) in starts.iter().enumerate() {
+        if !seen.insert(freq) {
+            return Ok(repeat_at(k, freq));
+        }
+    }
+
+    if n == 0 {
+        return Err(NoAnswer::new(1, "frequency never repeats"));
+    }
+    if drift == 0 {
+        return Ok(repeat_at(n, 0));
+    }
+
+    let mut classes: HashMap<isize, Vec<(isize, usize)>> = HashMap::new();
+    for (k, &freq) in starts.iter().enumerate() {
+        classes
+            .entry(freq.rem_euclid(drift.abs()))
+            .or_default()
+            .push((freq * drift.signum(), k));
+    }
+
+    classes
+        .values_mut()
+        .flat_map(|class| {
+            class.sort();
+            class
+                .windows(2)
+                .map(|pair| {
+                    let ((low, k), (high, _)) = (pair[0], pair[1]);
+                    let passes = ((high - low) / drift.abs()) as usize;
+                    (passes * n + k, high * drift.signum())
+                })
+                .collect::<Vec<_>>()
+        })
+        .min()
+        .map(|(k, frequency)| repeat_at(k, frequency))
+        .ok_or_else(|| NoAnswer::new(1, "frequency never repeats"))
 }
 
 #[aoc(day1, part2)]
-pub fn part2(input: &[isize]) -> isize {
+pub fn part2(input: &[isize]) -> Result<isize, NoAnswer> {
+    first_repeat(input).map(|repeat| repeat.frequency)
+}
+
+#[aoc(day1, part2, BruteForce)]
+pub fn part2_brute_force(input: &[isize]) -> Result<isize, NoAnswer> {
+    if !ever_repeats(input) {
+        return Err(NoAnswer::new(1, "frequency never repeats"));
+    }
+
     let mut freq = 0;
     let mut frequencies = HashSet::new();
 
@@ -20,10 +246,120 @@ pub fn part2(input: &[isize]) -> isize {
     for num in input.iter().cycle() {
         freq += num;
         if frequencies.contains(&freq) {
-            return freq;
+            return Ok(freq);
         }
         frequencies.insert(freq);
     }
 
-    unreachable!()
+    Err(NoAnswer::new(1, "frequency never repeats"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lcg::Lcg;
+
+    #[test]
+    fn malformed() {
+        assert_eq!(
+            input_frequency("+1\n-2\n+3x"),
+            Err(ParseError::new(1, 3, "+3x", EXPECTED))
+        );
+    }
+
+    #[test]
+    fn sample1() {
+        assert_eq!(
+            part1(&input_frequency("+1\n-2\n+3\n+1").unwrap()).unwrap(),
+            3
+        );
+    }
+
+    #[test]
+    fn streaming() {
+        let calibration = Calibration::from_reader(io::Cursor::new("+3\n-5\n+1\n+4\n-2")).unwrap();
+
+        assert_eq!(calibration.frequency(), 1);
+        assert_eq!(calibration.range(), (-2, 3));
+        assert_eq!(calibration.changes(), 5);
+        assert_eq!(calibration.first_repeat(), Some((3, 4)));
+
+        match Calibration::from_reader(io::Cursor::new("+3\nfive")) {
+            Err(CalibrationError::Parse(error)) => {
+                assert_eq!(error, ParseError::new(1, 2, "five", EXPECTED))
+            }
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn overflow() {
+        match Calibration::from_deltas(vec![1, i64::MAX, -5]) {
+            Err(CalibrationError::Overflow { line }) => assert_eq!(line, 2),
+            other => panic!("expected an overflow, got {:?}", other),
+        }
+
+        let mut calibration = Calibration::from_deltas(vec![1, 2]).unwrap();
+        assert!(calibration.push(i64::MAX).is_err());
+        assert_eq!(calibration.changes(), 2);
+        assert_eq!(calibration.frequency(), 3);
+    }
+
+    #[test]
+    fn sample2() {
+        assert_eq!(part2(&input_frequency("+1\n-2\n+3\n+1").unwrap()), Ok(2));
+        assert_eq!(part2(&[1, -1]), Ok(0));
+        assert_eq!(part2(&[3, 3, 4, -2, -4]), Ok(10));
+        assert_eq!(part2(&[-6, 3, 8, 5, -6]), Ok(5));
+        assert_eq!(part2(&[7, 7, -2, -7, -4]), Ok(14));
+    }
+
+    #[test]
+    fn never_repeats() {
+        let expected = Err(NoAnswer::new(1, "frequency never repeats"));
+
+        assert_eq!(part2(&[1]), expected);
+        assert_eq!(part2(&[3, 3]), expected);
+        assert_eq!(part2(&[]), expected);
+        assert_eq!(part2_brute_force(&[3, 3]), expected);
+    }
+
+    #[test]
+    fn repeat_position() {
+        assert_eq!(
+            first_repeat(&[1, -2, 3, 1]),
+            Ok(Repeat {
+                frequency: 2,
+                pass: 2,
+                line: 2,
+            })
+        );
+        assert_eq!(
+            first_repeat(&[1, -1]),
+            Ok(Repeat {
+                frequency: 0,
+                pass: 1,
+                line: 2,
+            })
+        );
+    }
+
+    // Runs both solvers on a spread of small generated inputs, and checks the
+    // reported position by replaying the changes up to it
+    #[test]
+    fn matches_brute_force() {
+        let mut lcg = Lcg::new(2018);
+
+        for _ in 0..2000 {
+            let len = lcg.below(8) as usize + 1;
+            let input: Vec<isize> = (0..len).map(|_| lcg.below(21) as isize - 10).collect();
+
+            assert_eq!(part2(&input), part2_brute_force(&input), "{:?}", input);
+            if let Ok(repeat) = first_repeat(&input) {
+                let changes = (repeat.pass - 1) * len + repeat.line;
+                let reached: isize = input.iter().cycle().take(changes).sum();
+                assert_eq!(reached, repeat.frequency, "{:?}", input);
+            }
+        }
+    }
 }
@@ -0,0 +1,279 @@
+// Day 10: The Stars Align //
+
+use crate::parse_error::{parse_lines, ParseError};
+use lazy_static;
+use regex::Regex;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Star {
+    pos: (i64, i64),
+    vel: (i64, i64),
+}
+
+impl Star {
+    fn position_at(&self, second: i64) -> (i64, i64) {
+        (
+            self.pos.0 + self.vel.0 * second,
+            self.pos.1 + self.vel.1 * second,
+        )
+    }
+}
+
+const GLYPH_WIDTH: usize = 6;
+const GLYPH_HEIGHT: usize = 10;
+const GLYPH_SPACING: usize = 2;
+
+// The 6x10 letters the puzzle draws its messages with, one row per string
+#[rustfmt::skip]
+const GLYPHS: [(char, [&str; GLYPH_HEIGHT]); 15] = [
+    ('A', ["..##..", ".#..#.", "#....#", "#....#", "#....#", "######", "#....#", "#....#", "#....#", "#....#"]),
+    ('B', ["#####.", "#....#", "#....#", "#....#", "#####.", "#....#", "#....#", "#....#", "#....#", "#####."]),
+    ('C', [".####.", "#....#", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#....#", ".####."]),
+    ('E', ["######", "#.....", "#.....", "#.....", "#####.", "#.....", "#.....", "#.....", "#.....", "######"]),
+    ('F', ["######", "#.....", "#.....", "#.....", "#####.", "#.....", "#.....", "#.....", "#.....", "#....."]),
+    ('G', [".####.", "#....#", "#.....", "#.....", "#.....", "#..###", "#....#", "#....#", "#...##", ".###.#"]),
+    ('H', ["#....#", "#....#", "#....#", "#....#", "######", "#....#", "#....#", "#....#", "#....#", "#....#"]),
+    ('J', ["...###", "....#.", "....#.", "....#.", "....#.", "....#.", "....#.", "#...#.", "#...#.", ".###.."]),
+    ('K', ["#....#", "#...#.", "#..#..", "#.#...", "##....", "##....", "#.#...", "#..#..", "#...#.", "#....#"]),
+    ('L', ["#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "#.....", "######"]),
+    ('N', ["#....#", "##...#", "##...#", "#.#..#", "#.#..#", "#..#.#", "#..#.#", "#...##", "#...##", "#....#"]),
+    ('P', ["#####.", "#....#", "#....#", "#....#", "#####.", "#.....", "#.....", "#.....", "#.....", "#....."]),
+    ('R', ["#####.", "#....#", "#....#", "#....#", "#####.", "#..#..", "#...#.", "#...#.", "#....#", "#....#"]),
+    ('X', ["#....#", "#....#", ".#..#.", ".#..#.", "..##..", "..##..", ".#..#.", ".#..#.", "#....#", "#....#"]),
+    ('Z', ["######", ".....#", ".....#", "....#.", "...#..", "..#...", ".#....", "#.....", "#.....", "######"]),
+];
+
+fn parse_star(text: &str) -> Option<Star> {
+    lazy_static::lazy_static! {
+        static ref RE: Regex =
+        Regex::new(r"^position=<\s*(-?\d+),\s*(-?\d+)> velocity=<\s*(-?\d+),\s*(-?\d+)>$").unwrap();
+    }
+
+    let cap = RE.captures(text.trim())?;
+    Some(Star {
+        pos: (cap[1].parse().ok()?, cap[2].parse().ok()?),
+        vel: (cap[3].parse().ok()?, cap[4].parse().ok()?),
+    })
+}
+
+#[aoc_generator(day10)]
+pub fn input_stars(input: &str) -> Result<Vec<Star>, ParseError> {
+    parse_lines(
+        input,
+        10,
+        "a star such as position=< 9,  1> velocity=< 0,  2>",
+        parse_star,
+    )
+}
+
+// Returns (min_x, max_x, min_y, max_y) of the sky at the given second, or
+// None for an empty sky
+fn bounds_at(stars: &[Star], second: i64) -> Option<(i64, i64, i64, i64)> {
+    let mut positions = stars.iter().map(|star| star.position_at(second));
+    let (x, y) = positions.next()?;
+    Some(positions.fold((x, x, y, y), |state, (x, y)| {
+        (
+            state.0.min(x),
+            state.1.max(x),
+            state.2.min(y),
+            state.3.max(y),
+        )
+    }))
+}
+
+// Width plus height of the bounding box. Each bound is a max or min of linear
+// functions of time, so this is convex and has no local minima to get stuck in
+fn extent_at(stars: &[Star], second: i64) -> i64 {
+    bounds_at(stars, second).map_or(0, |(min_x, max_x, min_y, max_y)| {
+        (max_x - min_x) + (max_y - min_y)
+    })
+}
+
+// The fastest rising and fastest falling stars both end up inside the message,
+// so the moment they share a row is within a few seconds of convergence.
+// Walking downhill from there lands on the exact second of the smallest sky
+fn converge_time(stars: &[Star]) -> i64 {
+    let slowest = stars.iter().min_by_key(|star| star.vel.1);
+    let fastest = stars.iter().max_by_key(|star| star.vel.1);
+
+    let mut second = match (slowest, fastest) {
+        (Some(a), Some(b)) if a.vel.1 != b.vel.1 => {
+            ((a.pos.1 - b.pos.1) / (b.vel.1 - a.vel.1)).max(0)
+        }
+        _ => 0,
+    };
+
+    while extent_at(stars, second + 1) < extent_at(stars, second) {
+        second += 1;
+    }
+    while second > 0 && extent_at(stars, second - 1) < extent_at(stars, second) {
+        second -= 1;
+    }
+    second
+}
+
+// Draws the sky as rows of '#' and '.', cropped to the bounding box. An empty
+// sky draws as nothing
+fn render(stars: &[Star], second: i64) -> String {
+    let (min_x, max_x, min_y, max_y) = match bounds_at(stars, second) {
+        Some(bounds) => bounds,
+        None => return String::new(),
+    };
+    let width = (max_x - min_x + 1) as usize;
+    let height = (max_y - min_y + 1) as usize;
+
+    let mut sky = vec![vec!['.'; width]; height];
+    for (x, y) in stars.iter().map(|star| star.position_at(second)) {
+        sky[(y - min_y) as usize][(x - min_x) as usize] = '#';
+    }
+
+    sky.iter()
+        .map(|row| row.iter().collect::<String>())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+// Reads a rendered message letter by letter, returning None if the picture
+// is not made of known glyphs
+fn ocr(rendered: &str) -> Option<String> {
+    let rows: Vec<&str> = rendered.lines().collect();
+    if rows.len() != GLYPH_HEIGHT {
+        return None;
+    }
+
+    let width = rows[0].len();
+    if !(width + GLYPH_SPACING).is_multiple_of(GLYPH_WIDTH + GLYPH_SPACING) {
+        return None;
+    }
+
+    (0..width)
+        .step_by(GLYPH_WIDTH + GLYPH_SPACING)
+        .map(|start| {
+            let glyph: Vec<&str> = rows
+                .iter()
+                .map(|row| &row[start..start + GLYPH_WIDTH])
+                .collect();
+            GLYPHS
+                .iter()
+                .find(|(_, pattern)| pattern[..] == glyph[..])
+                .map(|&(letter, _)| letter)
+        })
+        .collect()
+}
+
+#[aoc(day10, part1)]
+pub fn part1(input: &[Star]) -> String {
+    let rendered = render(input, converge_time(input));
+    ocr(&rendered).unwrap_or(rendered)
+}
+
+#[aoc(day10, part2)]
+pub fn part2(input: &[Star]) -> i64 {
+    converge_time(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static TEST_STR: &str = "position=< 9,  1> velocity=< 0,  2>\n\
+                             position=< 7,  0> velocity=<-1,  0>\n\
+                             position=< 3, -2> velocity=<-1,  1>\n\
+                             position=< 6, 10> velocity=<-2, -1>\n\
+                             position=< 2, -4> velocity=< 2,  2>\n\
+                             position=<-6, 10> velocity=< 2, -2>\n\
+                             position=< 1,  8> velocity=< 1, -1>\n\
+                             position=< 1,  7> velocity=< 1,  0>\n\
+                             position=<-3, 11> velocity=< 1, -2>\n\
+                             position=< 7,  6> velocity=<-1, -1>\n\
+                             position=<-2,  3> velocity=< 1,  0>\n\
+                             position=<-4,  3> velocity=< 2,  0>\n\
+                             position=<10, -3> velocity=<-1,  1>\n\
+                             position=< 5, 11> velocity=< 1, -2>\n\
+                             position=< 4,  7> velocity=< 0, -1>\n\
+                             position=< 8, -2> velocity=< 0,  1>\n\
+                             position=<15,  0> velocity=<-2,  0>\n\
+                             position=< 1,  6> velocity=< 1,  0>\n\
+                             position=< 8,  9> velocity=< 0, -1>\n\
+                             position=< 3,  3> velocity=<-1,  1>\n\
+                             position=< 0,  5> velocity=< 0, -1>\n\
+                             position=<-2,  2> velocity=< 2,  0>\n\
+                             position=< 5, -2> velocity=< 1,  2>\n\
+                             position=< 1,  4> velocity=< 2,  1>\n\
+                             position=<-2,  7> velocity=< 2, -2>\n\
+                             position=< 3,  6> velocity=<-1, -1>\n\
+                             position=< 5,  0> velocity=< 1,  0>\n\
+                             position=<-6,  0> velocity=< 2,  0>\n\
+                             position=< 5,  9> velocity=< 1, -2>\n\
+                             position=<14,  7> velocity=<-2,  0>\n\
+                             position=<-3,  6> velocity=< 2, -1>";
+
+    #[test]
+    fn grok_input() {
+        let stars = input_stars(TEST_STR).unwrap();
+        assert_eq!(stars.len(), 31);
+        assert_eq!(
+            stars[2],
+            Star {
+                pos: (3, -2),
+                vel: (-1, 1),
+            }
+        );
+    }
+
+    #[test]
+    fn empty_sky() {
+        let stars = input_stars("").unwrap();
+
+        assert_eq!(part1(&stars), "");
+        assert_eq!(part2(&stars), 0);
+    }
+
+    #[test]
+    fn malformed() {
+        assert_eq!(
+            input_stars("position=< 9,  1> velocity=< 0,  2>\nposition=< 9,  1>"),
+            Err(ParseError::new(
+                10,
+                2,
+                "position=< 9,  1>",
+                "a star such as position=< 9,  1> velocity=< 0,  2>"
+            ))
+        );
+    }
+
+    #[test]
+    fn read_glyphs() {
+        let word = "ZAX";
+        let rendered: Vec<String> = (0..GLYPH_HEIGHT)
+            .map(|row| {
+                word.chars()
+                    .map(|c| GLYPHS.iter().find(|(letter, _)| *letter == c).unwrap().1[row])
+                    .collect::<Vec<&str>>()
+                    .join("..")
+            })
+            .collect();
+
+        assert_eq!(ocr(&rendered.join("\n")), Some(word.to_string()));
+        assert_eq!(ocr("#...#\n#####"), None);
+    }
+
+    #[test]
+    fn sample1() {
+        let expected = "#...#..###\n\
+                        #...#...#.\n\
+                        #...#...#.\n\
+                        #####...#.\n\
+                        #...#...#.\n\
+                        #...#...#.\n\
+                        #...#...#.\n\
+                        #...#..###";
+
+        assert_eq!(part1(&input_stars(TEST_STR).unwrap()), expected);
+    }
+
+    #[test]
+    fn sample2() {
+        assert_eq!(part2(&input_stars(TEST_STR).unwrap()), 3);
+    }
+}
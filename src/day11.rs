@@ -0,0 +1,122 @@
+// Day 11: Chronal Charge //
+
+use crate::parse_error::ParseError;
+
+const GRID_SIZE: usize = 300;
+
+// Summed-area table of the fuel cell grid: `sums[y][x]` holds the total power
+// of every cell with coordinates at most (x, y). Row and column 0 are padding
+// so that squares touching the top or left edge need no special casing
+pub struct PowerGrid {
+    sums: Vec<Vec<i32>>,
+}
+
+solver_input!(PowerGrid);
+
+impl PowerGrid {
+    pub fn new(serial: i32) -> PowerGrid {
+        let mut sums = vec![vec![0; GRID_SIZE + 1]; GRID_SIZE + 1];
+        for y in 1..=GRID_SIZE {
+            for x in 1..=GRID_SIZE {
+                sums[y][x] =
+                    power_level(x as i32, y as i32, serial) + sums[y - 1][x] + sums[y][x - 1]
+                        - sums[y - 1][x - 1];
+            }
+        }
+        PowerGrid { sums }
+    }
+
+    // Total power of the size x size square whose top-left cell is (x, y)
+    fn square_power(&self, x: usize, y: usize, size: usize) -> i32 {
+        let (x0, y0) = (x - 1, y - 1);
+        let (x1, y1) = (x0 + size, y0 + size);
+        self.sums[y1][x1] - self.sums[y0][x1] - self.sums[y1][x0] + self.sums[y0][x0]
+    }
+
+    // Returns (x, y, total power) of the strongest square of the given size
+    fn best_square(&self, size: usize) -> (usize, usize, i32) {
+        let last = GRID_SIZE - size + 1;
+        (1..=last)
+            .flat_map(|y| (1..=last).map(move |x| (x, y)))
+            .map(|(x, y)| (x, y, self.square_power(x, y, size)))
+            .max_by_key(|&(_, _, power)| power)
+            .unwrap()
+    }
+}
+
+pub fn power_level(x: i32, y: i32, serial: i32) -> i32 {
+    let rack_id = x + 10;
+    let power = (rack_id * y + serial) * rack_id;
+    (power / 100) % 10 - 5
+}
+
+#[aoc_generator(day11)]
+pub fn input_grid(input: &str) -> Result<PowerGrid, ParseError> {
+    input
+        .trim()
+        .parse()
+        .map(PowerGrid::new)
+        .map_err(|_| ParseError::new(11, 1, input.trim(), "a grid serial number such as 18"))
+}
+
+#[aoc(day11, part1)]
+pub fn part1(input: &PowerGrid) -> String {
+    let (x, y, _) = input.best_square(3);
+    format!("{},{}", x, y)
+}
+
+#[aoc(day11, part2)]
+pub fn part2(input: &PowerGrid) -> String {
+    let (x, y, size, _) = (1..=GRID_SIZE)
+        .map(|size| {
+            let (x, y, power) = input.best_square(size);
+            (x, y, size, power)
+        })
+        .max_by_key(|&(_, _, _, power)| power)
+        .unwrap();
+    format!("{},{},{}", x, y, size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn power() {
+        assert_eq!(power_level(3, 5, 8), 4);
+        assert_eq!(power_level(122, 79, 57), -5);
+        assert_eq!(power_level(217, 196, 39), 0);
+        assert_eq!(power_level(101, 153, 71), 4);
+    }
+
+    #[test]
+    fn malformed() {
+        assert_eq!(
+            input_grid("serial 18").err(),
+            Some(ParseError::new(
+                11,
+                1,
+                "serial 18",
+                "a grid serial number such as 18"
+            ))
+        );
+    }
+
+    #[test]
+    fn square() {
+        assert_eq!(input_grid("18").unwrap().square_power(33, 45, 3), 29);
+        assert_eq!(input_grid("42").unwrap().square_power(21, 61, 3), 30);
+    }
+
+    #[test]
+    fn sample1() {
+        assert_eq!(part1(&input_grid("18").unwrap()), "33,45");
+        assert_eq!(part1(&input_grid("42").unwrap()), "21,61");
+    }
+
+    #[test]
+    fn sample2() {
+        assert_eq!(part2(&input_grid("18").unwrap()), "90,269,16");
+        assert_eq!(part2(&input_grid("42").unwrap()), "232,251,12");
+    }
+}
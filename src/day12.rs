@@ -0,0 +1,213 @@
+// Day 12: Subterranean Sustainability //
+
+use crate::parse_error::ParseError;
+use lazy_static;
+use regex::Regex;
+
+// A row of pots trimmed to the first and last plant. `offset` is the pot
+// number of `plants[0]`, so the row can grow in either direction
+#[derive(Debug, PartialEq, Clone)]
+pub struct Pots {
+    plants: Vec<bool>,
+    offset: i64,
+}
+
+impl Pots {
+    fn new(mut plants: Vec<bool>, mut offset: i64) -> Pots {
+        match plants.iter().position(|&plant| plant) {
+            Some(first) => {
+                let last = plants.iter().rposition(|&plant| plant).unwrap();
+                plants.truncate(last + 1);
+                plants.drain(..first);
+                offset += first as i64;
+            }
+            None => plants.clear(),
+        }
+        Pots { plants, offset }
+    }
+
+    // Slides a five pot window across the row, two pots past either end, and
+    // looks up each window in the rule table
+    fn step(&self, rules: &[bool; 32]) -> Pots {
+        let mut pattern = 0;
+        let next = (0..self.plants.len() + 4)
+            .map(|i| {
+                let plant = self.plants.get(i).cloned().unwrap_or(false);
+                pattern = ((pattern << 1) | plant as usize) & 0b11111;
+                rules[pattern]
+            })
+            .collect();
+        Pots::new(next, self.offset - 2)
+    }
+
+    fn count(&self) -> i64 {
+        self.plants.iter().filter(|&&plant| plant).count() as i64
+    }
+
+    fn sum(&self) -> i64 {
+        self.plants
+            .iter()
+            .zip(self.offset..)
+            .filter(|(&plant, _)| plant)
+            .map(|(_, number)| number)
+            .sum()
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Garden {
+    initial: Pots,
+    rules: [bool; 32],
+}
+
+solver_input!(Garden);
+
+fn parse_pots(text: &str) -> impl Iterator<Item = bool> + '_ {
+    text.chars().map(|c| c == '#')
+}
+
+fn parse_rule(text: &str) -> Option<(usize, bool)> {
+    lazy_static::lazy_static! {
+        static ref RE: Regex = Regex::new(r"^([#.]{5}) => ([#.])$").unwrap();
+    }
+
+    let cap = RE.captures(text.trim())?;
+    let pattern = parse_pots(&cap[1]).fold(0, |index, plant| (index << 1) | plant as usize);
+    Some((pattern, &cap[2] == "#"))
+}
+
+// Rules are indexed by their five pot pattern read as a binary number, with
+// the leftmost pot as the most significant bit
+#[aoc_generator(day12)]
+pub fn input_garden(input: &str) -> Result<Garden, ParseError> {
+    lazy_static::lazy_static! {
+        static ref INITIAL_RE: Regex = Regex::new(r"^initial state: ([#.]*)$").unwrap();
+    }
+
+    let first = input.lines().next().unwrap_or("");
+    let initial = INITIAL_RE
+        .captures(first.trim())
+        .ok_or_else(|| ParseError::new(12, 1, first, "a line such as initial state: #..#.#"))?;
+
+    let mut rules = [false; 32];
+    for (text, line) in input.lines().zip(1..).skip(1) {
+        if text.trim().is_empty() {
+            continue;
+        }
+        let (pattern, grows) = parse_rule(text)
+            .ok_or_else(|| ParseError::new(12, line, text, "a rule such as ##.## => #"))?;
+        rules[pattern] = grows;
+    }
+
+    Ok(Garden {
+        initial: Pots::new(parse_pots(&initial[1]).collect(), 0),
+        rules,
+    })
+}
+
+// Once a generation is the previous one moved along by a fixed number of
+// pots, every later generation is too, so the remaining generations only add
+// that shift once per plant per generation
+fn plant_sum(garden: &Garden, generations: u64) -> i64 {
+    let mut pots = garden.initial.clone();
+    for generation in 0..generations {
+        let next = pots.step(&garden.rules);
+        if next.plants == pots.plants {
+            let shift = next.offset - pots.offset;
+            let remaining = (generations - generation) as i64;
+            return pots.sum() + remaining * shift * pots.count();
+        }
+        pots = next;
+    }
+    pots.sum()
+}
+
+#[aoc(day12, part1)]
+pub fn part1(input: &Garden) -> i64 {
+    plant_sum(input, 20)
+}
+
+#[aoc(day12, part2)]
+pub fn part2(input: &Garden) -> i64 {
+    plant_sum(input, 50_000_000_000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static TEST_STR: &str = "initial state: #..#.#..##......###...###\n\
+                             \n\
+                             ...## => #\n\
+                             ..#.. => #\n\
+                             .#... => #\n\
+                             .#.#. => #\n\
+                             .#.## => #\n\
+                             .##.. => #\n\
+                             .#### => #\n\
+                             #.#.# => #\n\
+                             #.### => #\n\
+                             ##.#. => #\n\
+                             ##.## => #\n\
+                             ###.. => #\n\
+                             ###.# => #\n\
+                             ####. => #";
+
+    #[test]
+    fn grok_input() {
+        let garden = input_garden(TEST_STR).unwrap();
+        assert_eq!(garden.initial.offset, 0);
+        assert_eq!(garden.initial.count(), 11);
+        assert_eq!(garden.rules.iter().filter(|&&grows| grows).count(), 14);
+        assert!(garden.rules[0b00011]);
+        assert!(!garden.rules[0b00001]);
+    }
+
+    #[test]
+    fn malformed() {
+        assert_eq!(
+            input_garden("#..#.#\n\n...## => #"),
+            Err(ParseError::new(
+                12,
+                1,
+                "#..#.#",
+                "a line such as initial state: #..#.#"
+            ))
+        );
+        assert_eq!(
+            input_garden("initial state: #..#.#\n\n...## => #\n..#. => #"),
+            Err(ParseError::new(
+                12,
+                4,
+                "..#. => #",
+                "a rule such as ##.## => #"
+            ))
+        );
+    }
+
+    #[test]
+    fn generations() {
+        let garden = input_garden(TEST_STR).unwrap();
+        let third = garden
+            .initial
+            .step(&garden.rules)
+            .step(&garden.rules)
+            .step(&garden.rules);
+        assert_eq!(
+            third,
+            Pots::new(parse_pots("#.#...#..#.#....#..#..#...#").collect(), -1)
+        );
+    }
+
+    #[test]
+    fn steady_shift() {
+        let garden = input_garden("initial state: #....#\n\n...#. => #").unwrap();
+        assert_eq!(plant_sum(&garden, 5), -5);
+        assert_eq!(plant_sum(&garden, 1_000_000), -1_999_995);
+    }
+
+    #[test]
+    fn sample1() {
+        assert_eq!(part1(&input_garden(TEST_STR).unwrap()), 325);
+    }
+}
@@ -1,50 +1,344 @@
-use std::collections::HashMap;
+use crate::no_answer::NoAnswer;
+use std::collections::{HashMap, HashSet};
+use unicode_segmentation::UnicodeSegmentation;
+
+// For each tracked count, the number of IDs containing some letter exactly
+// that many times, in the order the counts were given
+#[derive(Debug, PartialEq, Clone)]
+pub struct Checksum {
+    pub classes: Vec<(usize, usize)>,
+}
+
+impl Checksum {
+    pub fn product(&self) -> usize {
+        self.classes.iter().map(|&(_, lines)| lines).product()
+    }
+}
+
+pub struct ChecksumBuilder {
+    counts: Vec<usize>,
+}
+
+impl ChecksumBuilder {
+    pub fn new(counts: &[usize]) -> ChecksumBuilder {
+        ChecksumBuilder {
+            counts: counts.to_vec(),
+        }
+    }
+
+    pub fn build(&self, input: &str) -> Checksum {
+        let mut classes: Vec<(usize, usize)> =
+            self.counts.iter().map(|&count| (count, 0)).collect();
+
+        for line in input.lines() {
+            let frequencies = letter_frequencies(line.trim());
+            for (count, lines) in classes.iter_mut() {
+                if frequencies.contains(count) {
+                    *lines += 1;
+                }
+            }
+        }
+
+        Checksum { classes }
+    }
+}
+
+// How often each letter of the ID occurs. Plain lowercase IDs are tallied in
+// a fixed array, without allocating; anything else is split into grapheme
+// clusters, so that a letter with a combining accent counts as one letter
+// rather than two
+enum LetterFrequencies {
+    Ascii([usize; 26]),
+    Graphemes(HashSet<usize>),
+}
+
+impl LetterFrequencies {
+    fn contains(&self, count: &usize) -> bool {
+        match self {
+            LetterFrequencies::Ascii(tally) => *count > 0 && tally.contains(count),
+            LetterFrequencies::Graphemes(counts) => counts.contains(count),
+        }
+    }
+}
+
+fn letter_frequencies(id: &str) -> LetterFrequencies {
+    if id.bytes().all(|b| b.is_ascii_lowercase()) {
+        let mut tally = [0; 26];
+        for b in id.bytes() {
+            tally[(b - b'a') as usize] += 1;
+        }
+        LetterFrequencies::Ascii(tally)
+    } else {
+        let mut tally: HashMap<&str, usize> = HashMap::new();
+        for letter in id.graphemes(true) {
+            *tally.entry(letter).or_insert(0) += 1;
+        }
+        LetterFrequencies::Graphemes(tally.values().cloned().collect())
+    }
+}
 
 #[aoc(day2, part1)]
 pub fn part1(input: &str) -> usize {
-    let (mut twos, mut threes) = (0, 0);
+    ChecksumBuilder::new(&[2, 3]).build(input).product()
+}
 
-    for line in input.lines() {
-        let mut frequencies_map = HashMap::new();
+// Two IDs that differ in exactly one position. Lines count from 1 and the
+// position counts characters from 0
+#[derive(Debug, PartialEq, Clone)]
+pub struct NearDuplicate {
+    pub first_line: usize,
+    pub second_line: usize,
+    pub position: usize,
+    pub common: String,
+}
+
+// IDs that differ only at some position are identical once the character at
+// that position is deleted. For each position in turn, every ID is filed
+// under its deletion, borrowed as the text before and after the deleted
+// character, so only IDs sharing a bucket ever get compared
+pub fn near_duplicates(input: &str) -> Vec<NearDuplicate> {
+    let ids: Vec<&str> = input.lines().map(|l| l.trim()).collect();
+    let longest = ids.iter().map(|id| id.chars().count()).max().unwrap_or(0);
+    let mut found = vec![];
 
-        for c in line.chars() {
-            let freq = frequencies_map.entry(c).or_insert(0);
-            *freq += 1
+    for position in 0..longest {
+        let mut buckets: HashMap<(&str, &str), Vec<usize>> = HashMap::new();
+        for (line, id) in ids.iter().enumerate() {
+            if let Some((start, c)) = id.char_indices().nth(position) {
+                let key = (&id[..start], &id[start + c.len_utf8()..]);
+                buckets.entry(key).or_default().push(line);
+            }
         }
 
-        if frequencies_map.iter().any(|(_, &count)| count == 2) {
-            twos += 1;
+        for ((before, after), lines) in buckets {
+            for (i, &first) in lines.iter().enumerate() {
+                for &second in &lines[i + 1..] {
+                    if ids[first] != ids[second] {
+                        found.push(NearDuplicate {
+                            first_line: first + 1,
+                            second_line: second + 1,
+                            position,
+                            common: format!("{}{}", before, after),
+                        });
+                    }
+                }
+            }
         }
+    }
+
+    found.sort_by_key(|pair| (pair.first_line, pair.second_line));
+    found
+}
 
-        if frequencies_map.iter().any(|(_, &count)| count == 3) {
-            threes += 1;
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Distance {
+    // Number of positions that differ, only defined for IDs of equal length
+    Hamming,
+    // Number of single character insertions, deletions and substitutions
+    Levenshtein,
+}
+
+impl Distance {
+    fn between(self, a: &[char], b: &[char]) -> Option<usize> {
+        match self {
+            Distance::Hamming if a.len() != b.len() => None,
+            Distance::Hamming => Some(a.iter().zip(b).filter(|(c1, c2)| c1 != c2).count()),
+            Distance::Levenshtein => Some(edit_table(a, b)[a.len()][b.len()]),
         }
     }
 
-    twos * threes
+    // The characters the two IDs keep in common under this distance: the
+    // matching positions for Hamming, and the characters left untouched by a
+    // cheapest edit for Levenshtein
+    fn common(self, a: &[char], b: &[char]) -> String {
+        match self {
+            Distance::Hamming => a
+                .iter()
+                .zip(b)
+                .filter(|(c1, c2)| c1 == c2)
+                .map(|(&c, _)| c)
+                .collect(),
+            Distance::Levenshtein => {
+                let table = edit_table(a, b);
+                let (mut i, mut j) = (a.len(), b.len());
+                let mut kept = vec![];
+                while i > 0 && j > 0 {
+                    if a[i - 1] == b[j - 1] && table[i][j] == table[i - 1][j - 1] {
+                        kept.push(a[i - 1]);
+                        i -= 1;
+                        j -= 1;
+                    } else if table[i][j] == table[i - 1][j - 1] + 1 {
+                        i -= 1;
+                        j -= 1;
+                    } else if table[i][j] == table[i - 1][j] + 1 {
+                        i -= 1;
+                    } else {
+                        j -= 1;
+                    }
+                }
+                kept.iter().rev().collect()
+            }
+        }
+    }
 }
 
-#[aoc(day2, part2)]
-pub fn part2(input: &str) -> String {
-    for (id1, val1) in input.lines().enumerate() {
-        for val2 in input.lines().skip(id1) {
-            let num_diff_chars = val1
-                .chars()
-                .zip(val2.chars())
-                .filter(|(c1, c2)| c1 != c2)
-                .count();
-
-            if num_diff_chars == 1 {
-                return val1
-                    .chars()
-                    .zip(val2.chars())
-                    .filter(|(c1, c2)| c1 == c2)
-                    .map(|(c, _)| c)
-                    .collect();
+// `table[i][j]` is the edit distance between the first i characters of `a`
+// and the first j characters of `b`
+fn edit_table(a: &[char], b: &[char]) -> Vec<Vec<usize>> {
+    let mut table = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in table.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in table[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let substitution = table[i - 1][j - 1] + (a[i - 1] != b[j - 1]) as usize;
+            table[i][j] = substitution
+                .min(table[i - 1][j] + 1)
+                .min(table[i][j - 1] + 1);
+        }
+    }
+    table
+}
+
+// Two distinct IDs within the matcher's distance of each other, with lines
+// counting from 1
+#[derive(Debug, PartialEq, Clone)]
+pub struct CloseMatch {
+    pub first_line: usize,
+    pub second_line: usize,
+    pub distance: usize,
+    pub common: String,
+}
+
+// Burkhard-Keller tree over the IDs. Each child hangs off its parent by its
+// distance to it, so by the triangle inequality a search for IDs within k of
+// a query at distance d from a node only needs the children at d-k..=d+k
+struct BkTree {
+    distance: Distance,
+    nodes: Vec<(usize, HashMap<usize, usize>)>,
+}
+
+impl BkTree {
+    fn new(distance: Distance) -> BkTree {
+        BkTree {
+            distance,
+            nodes: vec![],
+        }
+    }
+
+    fn insert(&mut self, ids: &[Vec<char>], line: usize) {
+        if self.nodes.is_empty() {
+            self.nodes.push((line, HashMap::new()));
+            return;
+        }
+
+        let mut node = 0;
+        loop {
+            let d = self
+                .distance
+                .between(&ids[self.nodes[node].0], &ids[line])
+                .unwrap();
+            match self.nodes[node].1.get(&d) {
+                Some(&child) => node = child,
+                None => {
+                    let child = self.nodes.len();
+                    self.nodes[node].1.insert(d, child);
+                    self.nodes.push((line, HashMap::new()));
+                    return;
+                }
             }
         }
     }
-    unreachable!()
+
+    // Returns (line, distance) of every stored ID within `max_distance`
+    fn search(
+        &self,
+        ids: &[Vec<char>],
+        query: &[char],
+        max_distance: usize,
+    ) -> Vec<(usize, usize)> {
+        let mut found = vec![];
+        let mut to_visit = if self.nodes.is_empty() {
+            vec![]
+        } else {
+            vec![0]
+        };
+
+        while let Some(node) = to_visit.pop() {
+            let (line, children) = &self.nodes[node];
+            let d = self.distance.between(&ids[*line], query).unwrap();
+            if d <= max_distance {
+                found.push((*line, d));
+            }
+            to_visit.extend(
+                children
+                    .iter()
+                    .filter(|(&edge, _)| edge + max_distance >= d && edge <= d + max_distance)
+                    .map(|(_, &child)| child),
+            );
+        }
+        found
+    }
+}
+
+pub struct Matcher {
+    distance: Distance,
+    max_distance: usize,
+}
+
+impl Matcher {
+    pub fn new(distance: Distance, max_distance: usize) -> Matcher {
+        Matcher {
+            distance,
+            max_distance,
+        }
+    }
+
+    // Each ID is looked up among the ones before it and then added to the
+    // index. Hamming distance is only defined between IDs of equal length, so
+    // those get a tree per length
+    pub fn find(&self, input: &str) -> Vec<CloseMatch> {
+        let ids: Vec<Vec<char>> = input.lines().map(|l| l.trim().chars().collect()).collect();
+        let mut trees: HashMap<usize, BkTree> = HashMap::new();
+        let mut found = vec![];
+
+        for (line, id) in ids.iter().enumerate() {
+            let tree_key = match self.distance {
+                Distance::Hamming => id.len(),
+                Distance::Levenshtein => 0,
+            };
+            let tree = trees
+                .entry(tree_key)
+                .or_insert_with(|| BkTree::new(self.distance));
+
+            for (earlier, distance) in tree.search(&ids, id, self.max_distance) {
+                if distance > 0 {
+                    found.push(CloseMatch {
+                        first_line: earlier + 1,
+                        second_line: line + 1,
+                        distance,
+                        common: self.distance.common(&ids[earlier], id),
+                    });
+                }
+            }
+            tree.insert(&ids, line);
+        }
+
+        found.sort_by_key(|pair| (pair.first_line, pair.second_line));
+        found
+    }
+}
+
+#[aoc(day2, part2)]
+pub fn part2(input: &str) -> Result<String, NoAnswer> {
+    near_duplicates(input)
+        .into_iter()
+        .next()
+        .map(|pair| pair.common)
+        .ok_or_else(|| NoAnswer::new(2, "no duplicate ID pair"))
 }
 
 #[cfg(test)]
@@ -54,16 +348,141 @@ mod tests {
     #[test]
     fn sample1() {
         assert_eq!(
-            part1(&"abcdef\nbababc\nabbcde\nabcccd\naabcdd\nabcdee\nababab"),
+            part1("abcdef\nbababc\nabbcde\nabcccd\naabcdd\nabcdee\nababab"),
             12
         );
     }
 
+    #[test]
+    fn count_classes() {
+        let checksum = ChecksumBuilder::new(&[2, 3, 4])
+            .build("abcdef\nbababc\nabbcde\nabcccd\naabcdd\nabcdee\nababab\naaaabb");
+
+        assert_eq!(checksum.classes, vec![(2, 5), (3, 3), (4, 1)]);
+        assert_eq!(checksum.product(), 15);
+    }
+
+    #[test]
+    fn graphemes() {
+        let checksum = ChecksumBuilder::new(&[2, 3]).build("e\u{301}e\nße\u{301}ße\u{301}\nxÿÿÿ");
+
+        assert_eq!(checksum.classes, vec![(2, 1), (3, 1)]);
+    }
+
     #[test]
     fn sample2() {
         assert_eq!(
-            part2(&"abcde\nfghij\nklmno\npqrst\nfguij\naxcye\nwvxyz"),
-            "fgij"
+            part2("abcde\nfghij\nklmno\npqrst\nfguij\naxcye\nwvxyz"),
+            Ok("fgij".to_string())
+        );
+    }
+
+    #[test]
+    fn all_pairs() {
+        assert_eq!(
+            near_duplicates("abcde\nfghij\nabxde\nfguij\nabcde\nfghij"),
+            vec![
+                NearDuplicate {
+                    first_line: 1,
+                    second_line: 3,
+                    position: 2,
+                    common: "abde".to_string(),
+                },
+                NearDuplicate {
+                    first_line: 2,
+                    second_line: 4,
+                    position: 2,
+                    common: "fgij".to_string(),
+                },
+                NearDuplicate {
+                    first_line: 3,
+                    second_line: 5,
+                    position: 2,
+                    common: "abde".to_string(),
+                },
+                NearDuplicate {
+                    first_line: 4,
+                    second_line: 6,
+                    position: 2,
+                    common: "fgij".to_string(),
+                },
+            ]
+        );
+        assert_eq!(near_duplicates("ßab\nßax")[0].common, "ßa");
+    }
+
+    #[test]
+    fn hamming() {
+        let matches = Matcher::new(Distance::Hamming, 2).find("abcde\nfghij\nabxye\nfguij\nabc");
+
+        assert_eq!(
+            matches,
+            vec![
+                CloseMatch {
+                    first_line: 1,
+                    second_line: 3,
+                    distance: 2,
+                    common: "abe".to_string(),
+                },
+                CloseMatch {
+                    first_line: 2,
+                    second_line: 4,
+                    distance: 1,
+                    common: "fgij".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn levenshtein() {
+        let matches =
+            Matcher::new(Distance::Levenshtein, 1).find("abcde\nabde\nxabcde\nfghij\nabcde");
+
+        assert_eq!(
+            matches
+                .iter()
+                .map(|m| (m.first_line, m.second_line, m.distance, m.common.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (1, 2, 1, "abde"),
+                (1, 3, 1, "abcde"),
+                (2, 5, 1, "abde"),
+                (3, 5, 1, "abcde"),
+            ]
+        );
+        assert_eq!(
+            Distance::Levenshtein.common(
+                &['k', 'i', 't', 't', 'e', 'n'],
+                &['s', 'i', 't', 't', 'i', 'n', 'g']
+            ),
+            "ittn"
+        );
+    }
+
+    #[test]
+    fn matcher_agrees_with_buckets() {
+        let input = "abcde\nfghij\nklmno\npqrst\nfguij\naxcye\nwvxyz\nabcdf";
+        let from_buckets: Vec<(usize, usize, String)> = near_duplicates(input)
+            .into_iter()
+            .map(|pair| (pair.first_line, pair.second_line, pair.common))
+            .collect();
+
+        for distance in [Distance::Hamming, Distance::Levenshtein].iter() {
+            let from_tree: Vec<(usize, usize, String)> = Matcher::new(*distance, 1)
+                .find(input)
+                .into_iter()
+                .map(|pair| (pair.first_line, pair.second_line, pair.common))
+                .collect();
+            assert_eq!(from_tree, from_buckets);
+        }
+    }
+
+    #[test]
+    fn no_pair() {
+        assert_eq!(
+            part2("abcde\nfghij\nabxye"),
+            Err(NoAnswer::new(2, "no duplicate ID pair"))
         );
     }
 }
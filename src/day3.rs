@@ -1,8 +1,11 @@
 // Day 3: No Matter How You Slice It //
 
+use crate::no_answer::NoAnswer;
+use crate::parse_error::{parse_lines, ParseError};
 use lazy_static;
 use regex::Regex;
-use std::collections::HashMap;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
 
 #[derive(Debug, PartialEq)]
 pub struct Rectangle {
@@ -13,66 +16,593 @@ pub struct Rectangle {
     h: usize,
 }
 
-fn sheet_builder(rectangles: &[Rectangle]) -> HashMap<(usize, usize), usize> {
-    let mut fabric = HashMap::new();
-    for rectangle in rectangles {
-        for (x, y) in occupancy_checker(rectangle) {
-            let position = fabric.entry((x, y)).or_insert(0);
-            *position += 1;
+impl Rectangle {
+    fn right(&self) -> usize {
+        self.x + self.w
+    }
+
+    fn bottom(&self) -> usize {
+        self.y + self.h
+    }
+
+    fn is_empty(&self) -> bool {
+        self.w == 0 || self.h == 0
+    }
+
+    fn overlaps(&self, other: &Rectangle) -> bool {
+        self.x < other.right()
+            && other.x < self.right()
+            && self.y < other.bottom()
+            && other.y < self.bottom()
+    }
+
+    fn overlap_area(&self, other: &Rectangle) -> usize {
+        let w = self
+            .right()
+            .min(other.right())
+            .saturating_sub(self.x.max(other.x));
+        let h = self
+            .bottom()
+            .min(other.bottom())
+            .saturating_sub(self.y.max(other.y));
+        w * h
+    }
+}
+
+// Segment tree over the compressed y coordinates of the claims. Leaf i stands
+// for the rows ys[i]..ys[i + 1]; every node keeps how many claims cover its
+// whole span, and how many of its rows are covered at least once and twice
+struct CoverageTree {
+    ys: Vec<usize>,
+    cover: Vec<usize>,
+    once: Vec<usize>,
+    twice: Vec<usize>,
+}
+
+impl CoverageTree {
+    fn new(ys: Vec<usize>) -> CoverageTree {
+        let size = 4 * ys.len();
+        CoverageTree {
+            ys,
+            cover: vec![0; size],
+            once: vec![0; size],
+            twice: vec![0; size],
+        }
+    }
+
+    fn doubly_covered(&self) -> usize {
+        self.twice[1]
+    }
+
+    // Adds (or with `added` false, removes) one claim over the leaves lo..hi
+    fn update(&mut self, lo: usize, hi: usize, added: bool) {
+        self.update_node(1, 0, self.ys.len() - 1, lo, hi, added);
+    }
+
+    fn update_node(
+        &mut self,
+        node: usize,
+        start: usize,
+        end: usize,
+        lo: usize,
+        hi: usize,
+        added: bool,
+    ) {
+        if hi <= start || end <= lo {
+            return;
         }
+        if lo <= start && end <= hi {
+            if added {
+                self.cover[node] += 1;
+            } else {
+                self.cover[node] -= 1;
+            }
+        } else {
+            let mid = (start + end) / 2;
+            self.update_node(2 * node, start, mid, lo, hi, added);
+            self.update_node(2 * node + 1, mid, end, lo, hi, added);
+        }
+
+        let span = self.ys[end] - self.ys[start];
+        let leaf = end - start == 1;
+        let children = |counts: &[usize]| {
+            if leaf {
+                0
+            } else {
+                counts[2 * node] + counts[2 * node + 1]
+            }
+        };
+        let (once, twice) = match self.cover[node] {
+            0 => (children(&self.once), children(&self.twice)),
+            1 => (span, children(&self.once)),
+            _ => (span, span),
+        };
+        self.once[node] = once;
+        self.twice[node] = twice;
+    }
+}
+
+// Sweeps a vertical line across the fabric. Between two claim edges the area
+// claimed twice grows by the rows covered twice, times the distance moved
+fn overlapped_area(rectangles: &[Rectangle]) -> usize {
+    let claims: Vec<&Rectangle> = rectangles.iter().filter(|r| !r.is_empty()).collect();
+
+    let mut ys: Vec<usize> = claims.iter().flat_map(|r| vec![r.y, r.bottom()]).collect();
+    ys.sort_unstable();
+    ys.dedup();
+    let row = |y: usize| ys.binary_search(&y).unwrap();
+
+    let mut edges: Vec<(usize, bool, usize, usize)> = claims
+        .iter()
+        .flat_map(|r| {
+            vec![
+                (r.x, true, row(r.y), row(r.bottom())),
+                (r.right(), false, row(r.y), row(r.bottom())),
+            ]
+        })
+        .collect();
+    edges.sort_unstable();
+
+    if ys.len() < 2 {
+        return 0;
+    }
+    let mut tree = CoverageTree::new(ys.clone());
+    let mut area = 0;
+    let mut last_x = 0;
+    for (x, added, lo, hi) in edges {
+        area += tree.doubly_covered() * (x - last_x);
+        tree.update(lo, hi, added);
+        last_x = x;
     }
-    fabric
+    area
 }
 
-fn occupancy_checker(rectangle: &Rectangle) -> Vec<(usize, usize)> {
-    let mut occupancy = Vec::new();
-    for x in rectangle.x..(rectangle.w + rectangle.x) {
-        for y in rectangle.y..(rectangle.h + rectangle.y) {
-            occupancy.push((x, y));
+// Returns the index pairs of every two claims sharing some square inch, for
+// the conflict report. Claims are visited from left to right and only
+// compared with the claims still open at that point
+fn overlapping_pairs(rectangles: &[Rectangle]) -> Vec<(usize, usize)> {
+    let mut order: Vec<usize> = (0..rectangles.len())
+        .filter(|&i| !rectangles[i].is_empty())
+        .collect();
+    order.sort_by_key(|&i| rectangles[i].x);
+
+    let mut open: Vec<usize> = vec![];
+    let mut pairs = vec![];
+    for i in order {
+        open.retain(|&j| rectangles[j].right() > rectangles[i].x);
+        for &j in &open {
+            if rectangles[i].overlaps(&rectangles[j]) {
+                pairs.push((i.min(j), i.max(j)));
+            }
         }
+        open.push(i);
     }
-    occupancy
+    pairs
 }
 
-fn reg_exp_helper(text: &str) -> Rectangle {
-    lazy_static::lazy_static! {
-        static ref RE: Regex =
-        Regex::new(r"(\d+) @ (\d+),(\d+): (\d+)x(\d+)").unwrap();
+// Segment tree over the compressed y coordinates of the claims, answering
+// whether a claim being added or removed by the sweep meets any other. It
+// keeps how many open claims cover each row, and the latest opening stamp
+// of any claim that has covered it
+struct ContactTree {
+    leaves: usize,
+    count_add: Vec<usize>,
+    count_max: Vec<usize>,
+    stamp_tag: Vec<usize>,
+    stamp_max: Vec<usize>,
+}
+
+impl ContactTree {
+    fn new(leaves: usize) -> ContactTree {
+        let size = 4 * leaves.max(1);
+        ContactTree {
+            leaves,
+            count_add: vec![0; size],
+            count_max: vec![0; size],
+            stamp_tag: vec![0; size],
+            stamp_max: vec![0; size],
+        }
     }
-    let cap = RE.captures(text).unwrap();
-    Rectangle {
-        id: cap.get(1).unwrap().as_str().parse::<usize>().unwrap(),
-        x: cap.get(2).unwrap().as_str().parse::<usize>().unwrap(),
-        y: cap.get(3).unwrap().as_str().parse::<usize>().unwrap(),
-        w: cap.get(4).unwrap().as_str().parse::<usize>().unwrap(),
-        h: cap.get(5).unwrap().as_str().parse::<usize>().unwrap(),
+
+    // Opens (or with `stamp` None, closes) one claim over the leaves lo..hi
+    fn update(&mut self, lo: usize, hi: usize, stamp: Option<usize>) {
+        self.update_node(1, 0, self.leaves, lo, hi, stamp);
+    }
+
+    fn update_node(
+        &mut self,
+        node: usize,
+        start: usize,
+        end: usize,
+        lo: usize,
+        hi: usize,
+        stamp: Option<usize>,
+    ) {
+        if hi <= start || end <= lo {
+            return;
+        }
+        if lo <= start && end <= hi {
+            match stamp {
+                Some(stamp) => {
+                    self.count_add[node] += 1;
+                    self.stamp_tag[node] = self.stamp_tag[node].max(stamp);
+                }
+                None => self.count_add[node] -= 1,
+            }
+        } else {
+            let mid = (start + end) / 2;
+            self.update_node(2 * node, start, mid, lo, hi, stamp);
+            self.update_node(2 * node + 1, mid, end, lo, hi, stamp);
+        }
+
+        let leaf = end - start == 1;
+        let children = |values: &[usize]| {
+            if leaf {
+                0
+            } else {
+                values[2 * node].max(values[2 * node + 1])
+            }
+        };
+        self.count_max[node] = self.count_add[node] + children(&self.count_max);
+        self.stamp_max[node] = self.stamp_tag[node].max(children(&self.stamp_max));
+    }
+
+    // The most open claims over any row of lo..hi, and the latest stamp of a
+    // claim that has covered any of them
+    fn query(&self, lo: usize, hi: usize) -> (usize, usize) {
+        self.query_node(1, 0, self.leaves, lo, hi)
+    }
+
+    fn query_node(
+        &self,
+        node: usize,
+        start: usize,
+        end: usize,
+        lo: usize,
+        hi: usize,
+    ) -> (usize, usize) {
+        if hi <= start || end <= lo {
+            return (0, 0);
+        }
+        if lo <= start && end <= hi {
+            return (self.count_max[node], self.stamp_max[node]);
+        }
+        let mid = (start + end) / 2;
+        let (left_count, left_stamp) = self.query_node(2 * node, start, mid, lo, hi);
+        let (right_count, right_stamp) = self.query_node(2 * node + 1, mid, end, lo, hi);
+        (
+            self.count_add[node] + left_count.max(right_count),
+            self.stamp_tag[node].max(left_stamp).max(right_stamp),
+        )
     }
 }
 
-#[aoc_generator(day3)]
-pub fn input_claims(input: &str) -> Vec<Rectangle> {
-    input.lines().map(|l| reg_exp_helper(l)).collect()
+// Whether each claim shares some square inch with another, found in a single
+// sweep without comparing claims pairwise. Claims open in stamp order, and
+// closing edges come before opening ones at the same x. A claim meets every
+// claim still open when it opens, which the cover counts show, and every
+// claim opened after it but before it closes, which the stamps show
+fn overlapped_claims(rectangles: &[Rectangle]) -> Vec<bool> {
+    let mut ys: Vec<usize> = rectangles
+        .iter()
+        .filter(|r| !r.is_empty())
+        .flat_map(|r| vec![r.y, r.bottom()])
+        .collect();
+    ys.sort_unstable();
+    ys.dedup();
+    let row = |y: usize| ys.binary_search(&y).unwrap();
+
+    let mut edges: Vec<(usize, bool, usize)> = (0..rectangles.len())
+        .filter(|&i| !rectangles[i].is_empty())
+        .flat_map(|i| {
+            vec![
+                (rectangles[i].x, true, i),
+                (rectangles[i].right(), false, i),
+            ]
+        })
+        .collect();
+    edges.sort_unstable();
+
+    let mut tree = ContactTree::new(ys.len().saturating_sub(1));
+    let mut stamps = vec![0; rectangles.len()];
+    let mut overlapped = vec![false; rectangles.len()];
+    for (stamp, (_, opening, i)) in edges.into_iter().enumerate() {
+        let (lo, hi) = (row(rectangles[i].y), row(rectangles[i].bottom()));
+        let (count, latest) = tree.query(lo, hi);
+        if opening {
+            overlapped[i] |= count > 0;
+            stamps[i] = stamp + 1;
+            tree.update(lo, hi, Some(stamp + 1));
+        } else {
+            overlapped[i] |= latest > stamps[i];
+            tree.update(lo, hi, None);
+        }
+    }
+    overlapped
 }
 
-#[aoc(day3, part1)]
-pub fn part1(input: &Vec<Rectangle>) -> usize {
-    sheet_builder(input)
+// IDs of the claims that share no square inch with any other, in input order
+pub fn clean_claims(rectangles: &[Rectangle]) -> Vec<usize> {
+    rectangles
         .iter()
-        .filter(|(_, &count)| count > 1)
-        .count()
+        .zip(overlapped_claims(rectangles))
+        .filter(|(_, overlapped)| !overlapped)
+        .map(|(rectangle, _)| rectangle.id)
+        .collect()
 }
 
-#[aoc(day3, part2)]
-pub fn part2(input: &Vec<Rectangle>) -> usize {
-    let fabric = sheet_builder(input);
+// Two claims sharing `area` square inches, with the lower ID first
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Conflict {
+    pub first: usize,
+    pub second: usize,
+    pub area: usize,
+}
+
+// Every clash between claims, for resolving them by hand. Groups are the sets
+// of claims linked by a chain of conflicts, so moving a claim can only affect
+// the others in its group
+#[derive(Debug, PartialEq)]
+pub struct ConflictReport {
+    conflicts: Vec<Conflict>,
+    groups: Vec<Vec<usize>>,
+    group_of: BTreeMap<usize, usize>,
+}
 
-    for rectangle in input {
-        let occupancy = occupancy_checker(rectangle);
-        if occupancy.iter().all(|(x, y)| fabric[&(*x, *y)] == 1) {
-            return rectangle.id;
+// Union-find over claim IDs, joining smaller sets into larger ones and
+// finding roots with a loop rather than recursion
+#[derive(Default)]
+struct ClaimSets {
+    parent: BTreeMap<usize, usize>,
+    size: BTreeMap<usize, usize>,
+}
+
+impl ClaimSets {
+    fn root(&mut self, id: usize) -> usize {
+        let mut top = *self.parent.entry(id).or_insert(id);
+        while self.parent[&top] != top {
+            top = self.parent[&top];
+        }
+
+        let mut step = id;
+        while step != top {
+            let up = self.parent[&step];
+            self.parent.insert(step, top);
+            step = up;
+        }
+        top
+    }
+
+    fn join(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.root(a), self.root(b));
+        if a == b {
+            return;
+        }
+        let size_a = *self.size.get(&a).unwrap_or(&1);
+        let size_b = *self.size.get(&b).unwrap_or(&1);
+        let (small, large) = if size_a < size_b { (a, b) } else { (b, a) };
+        self.parent.insert(small, large);
+        self.size.insert(large, size_a + size_b);
+    }
+
+    // Every set, sorted, ordered by its lowest ID
+    fn groups(&mut self) -> Vec<Vec<usize>> {
+        let ids: Vec<usize> = self.parent.keys().cloned().collect();
+        let mut by_root: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        for id in ids {
+            let top = self.root(id);
+            by_root.entry(top).or_default().push(id);
         }
+        let mut groups: Vec<Vec<usize>> = by_root.into_values().collect();
+        groups.sort_unstable_by_key(|group| group[0]);
+        groups
     }
-    unreachable!()
+}
+
+impl ConflictReport {
+    pub fn new(rectangles: &[Rectangle]) -> ConflictReport {
+        let mut conflicts: Vec<Conflict> = overlapping_pairs(rectangles)
+            .into_iter()
+            .map(|(i, j)| {
+                let (a, b) = (&rectangles[i], &rectangles[j]);
+                Conflict {
+                    first: a.id.min(b.id),
+                    second: a.id.max(b.id),
+                    area: a.overlap_area(b),
+                }
+            })
+            .collect();
+        conflicts.sort_by_key(|conflict| (conflict.first, conflict.second));
+
+        let mut sets = ClaimSets::default();
+        for conflict in &conflicts {
+            sets.join(conflict.first, conflict.second);
+        }
+        let groups = sets.groups();
+        let group_of = groups
+            .iter()
+            .enumerate()
+            .flat_map(|(group, ids)| ids.iter().map(move |&id| (id, group)))
+            .collect();
+
+        ConflictReport {
+            conflicts,
+            groups,
+            group_of,
+        }
+    }
+
+    // All conflicts, ordered by the IDs of the claims involved
+    pub fn conflicts(&self) -> &[Conflict] {
+        &self.conflicts
+    }
+
+    // The claims overlapping claim `id`, with the area each one shares with it
+    pub fn conflicts_of(&self, id: usize) -> Vec<(usize, usize)> {
+        self.conflicts
+            .iter()
+            .filter_map(|conflict| {
+                if conflict.first == id {
+                    Some((conflict.second, conflict.area))
+                } else if conflict.second == id {
+                    Some((conflict.first, conflict.area))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    // Groups of claims linked by conflicts, each sorted and ordered by its
+    // lowest ID. Claims without any conflict are left out
+    pub fn groups(&self) -> &[Vec<usize>] {
+        &self.groups
+    }
+
+    // One row per conflict, numbering the groups from 1 in the order above
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("first,second,area,group\n");
+        for conflict in &self.conflicts {
+            let group = self.group_of[&conflict.first];
+            csv += &format!(
+                "{},{},{},{}\n",
+                conflict.first,
+                conflict.second,
+                conflict.area,
+                group + 1
+            );
+        }
+        csv
+    }
+}
+
+const EMPTY: [u8; 3] = [32, 32, 32];
+const CLAIMED_ONCE: [u8; 3] = [70, 110, 200];
+const CLAIMED_MORE: [u8; 3] = [220, 50, 40];
+const CLEAN: [u8; 3] = [90, 220, 90];
+
+// Picture of the fabric with one pixel per square inch, coloured by how many
+// claims cover it. The claims that overlap nothing are drawn over the top in
+// their own colour
+#[derive(Debug, PartialEq)]
+pub struct Heatmap {
+    width: usize,
+    height: usize,
+    rgb: Vec<u8>,
+}
+
+impl Heatmap {
+    pub fn new(rectangles: &[Rectangle]) -> Heatmap {
+        let width = rectangles.iter().map(|r| r.right()).max().unwrap_or(0);
+        let height = rectangles.iter().map(|r| r.bottom()).max().unwrap_or(0);
+
+        // Difference array: each claim adds one at its top left corner and
+        // cancels it past its other corners, so prefix sums give the counts
+        let mut counts = vec![0isize; (width + 1) * (height + 1)];
+        let cell = |x: usize, y: usize| y * (width + 1) + x;
+        for r in rectangles.iter().filter(|r| !r.is_empty()) {
+            counts[cell(r.x, r.y)] += 1;
+            counts[cell(r.right(), r.y)] -= 1;
+            counts[cell(r.x, r.bottom())] -= 1;
+            counts[cell(r.right(), r.bottom())] += 1;
+        }
+        for y in 0..=height {
+            for x in 0..=width {
+                let left = if x > 0 { counts[cell(x - 1, y)] } else { 0 };
+                let up = if y > 0 { counts[cell(x, y - 1)] } else { 0 };
+                let diagonal = if x > 0 && y > 0 {
+                    counts[cell(x - 1, y - 1)]
+                } else {
+                    0
+                };
+                counts[cell(x, y)] += left + up - diagonal;
+            }
+        }
+
+        let mut rgb = Vec::with_capacity(width * height * 3);
+        for y in 0..height {
+            for x in 0..width {
+                rgb.extend_from_slice(match counts[cell(x, y)] {
+                    0 => &EMPTY,
+                    1 => &CLAIMED_ONCE,
+                    _ => &CLAIMED_MORE,
+                });
+            }
+        }
+
+        let clean = clean_claims(rectangles);
+        for r in rectangles.iter().filter(|r| clean.contains(&r.id)) {
+            for y in r.y..r.bottom() {
+                for x in r.x..r.right() {
+                    let at = (y * width + x) * 3;
+                    rgb[at..at + 3].copy_from_slice(&CLEAN);
+                }
+            }
+        }
+
+        Heatmap { width, height, rgb }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    // The colour of the square inch at x, y, as red, green and blue
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 3] {
+        let at = (y * self.width + x) * 3;
+        [self.rgb[at], self.rgb[at + 1], self.rgb[at + 2]]
+    }
+
+    // Writes a binary PPM, which most image viewers open without any help
+    pub fn write_ppm<W: Write>(&self, mut out: W) -> io::Result<()> {
+        write!(out, "P6\n{} {}\n255\n", self.width, self.height)?;
+        out.write_all(&self.rgb)
+    }
+
+    #[cfg(feature = "png")]
+    pub fn write_png<W: Write>(&self, out: W) -> Result<(), png::EncodingError> {
+        let mut encoder = png::Encoder::new(out, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&self.rgb)
+    }
+}
+
+fn reg_exp_helper(text: &str) -> Option<Rectangle> {
+    lazy_static::lazy_static! {
+        static ref RE: Regex =
+        Regex::new(r"^#(\d+) @ (\d+),(\d+): (\d+)x(\d+)$").unwrap();
+    }
+    let cap = RE.captures(text.trim())?;
+    Some(Rectangle {
+        id: cap[1].parse().ok()?,
+        x: cap[2].parse().ok()?,
+        y: cap[3].parse().ok()?,
+        w: cap[4].parse().ok()?,
+        h: cap[5].parse().ok()?,
+    })
+}
+
+#[aoc_generator(day3)]
+pub fn input_claims(input: &str) -> Result<Vec<Rectangle>, ParseError> {
+    parse_lines(input, 3, "a claim such as #1 @ 1,3: 4x4", reg_exp_helper)
+}
+
+#[aoc(day3, part1)]
+pub fn part1(input: &[Rectangle]) -> usize {
+    overlapped_area(input)
+}
+
+#[aoc(day3, part2)]
+pub fn part2(input: &[Rectangle]) -> Result<usize, NoAnswer> {
+    clean_claims(input)
+        .into_iter()
+        .next()
+        .ok_or_else(|| NoAnswer::new(3, "no non-overlapping claim"))
 }
 
 #[cfg(test)]
@@ -109,16 +639,143 @@ mod tests {
             },
         ];
 
-        assert_eq!(input_claims(TEST_STR), expected);
+        assert_eq!(input_claims(TEST_STR), Ok(expected));
+    }
+
+    #[test]
+    fn malformed() {
+        assert_eq!(
+            input_claims("#1 @ 1,3: 4x4\n#2 @ 3,1 4x4"),
+            Err(ParseError::new(
+                3,
+                2,
+                "#2 @ 3,1 4x4",
+                "a claim such as #1 @ 1,3: 4x4"
+            ))
+        );
     }
 
     #[test]
     fn sample1() {
-        assert_eq!(part1(&input_claims(TEST_STR)), 4);
+        assert_eq!(part1(&input_claims(TEST_STR).unwrap()), 4);
     }
 
     #[test]
     fn sample2() {
-        assert_eq!(part2(&input_claims(TEST_STR)), 3);
+        assert_eq!(part2(&input_claims(TEST_STR).unwrap()), Ok(3));
+    }
+
+    #[test]
+    fn large_claims() {
+        let claims = input_claims(
+            "#1 @ 0,0: 1000000x1000000\n\
+             #2 @ 999999,999999: 5x5\n\
+             #3 @ 500000,0: 1000000x2\n\
+             #4 @ 2000000,0: 3x3\n\
+             #5 @ 7,7: 0x4",
+        )
+        .unwrap();
+
+        assert_eq!(part1(&claims), 1 + 500_000 * 2);
+        assert_eq!(clean_claims(&claims), vec![4, 5]);
+    }
+
+    #[test]
+    fn conflicts() {
+        let report = ConflictReport::new(
+            &input_claims(
+                "#1 @ 1,3: 4x4\n\
+                 #2 @ 3,1: 4x4\n\
+                 #3 @ 5,5: 2x2\n\
+                 #4 @ 20,20: 3x3\n\
+                 #5 @ 0,0: 2x4\n\
+                 #6 @ 21,22: 5x5",
+            )
+            .unwrap(),
+        );
+
+        assert_eq!(report.conflicts_of(1), vec![(2, 4), (5, 1)]);
+        assert_eq!(report.conflicts_of(3), vec![]);
+        assert_eq!(report.groups(), &[vec![1, 2, 5], vec![4, 6]]);
+        assert_eq!(
+            report.to_csv(),
+            "first,second,area,group\n\
+             1,2,4,1\n\
+             1,5,1,1\n\
+             4,6,2,2\n"
+        );
+    }
+
+    #[test]
+    fn long_chain() {
+        let chain: Vec<String> = (0..20_000)
+            .map(|i| format!("#{} @ {},0: 2x1", 20_000 - i, i))
+            .collect();
+        let report = ConflictReport::new(&input_claims(&chain.join("\n")).unwrap());
+
+        assert_eq!(report.groups().len(), 1);
+        assert_eq!(report.groups()[0].len(), 20_000);
+        assert_eq!(report.to_csv().lines().count(), 20_000);
+    }
+
+    #[test]
+    fn heatmap() {
+        let heatmap = Heatmap::new(&input_claims(TEST_STR).unwrap());
+
+        assert_eq!((heatmap.width(), heatmap.height()), (7, 7));
+        assert_eq!(heatmap.pixel(0, 0), EMPTY);
+        assert_eq!(heatmap.pixel(1, 3), CLAIMED_ONCE);
+        assert_eq!(heatmap.pixel(3, 3), CLAIMED_MORE);
+        assert_eq!(heatmap.pixel(6, 6), CLEAN);
+
+        let mut ppm = vec![];
+        heatmap.write_ppm(&mut ppm).unwrap();
+        assert!(ppm.starts_with(b"P6\n7 7\n255\n"));
+        assert_eq!(ppm.len(), 11 + 7 * 7 * 3);
+    }
+
+    #[cfg(feature = "png")]
+    #[test]
+    fn heatmap_png() {
+        let mut png = vec![];
+        Heatmap::new(&input_claims(TEST_STR).unwrap())
+            .write_png(&mut png)
+            .unwrap();
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+    }
+
+    #[test]
+    fn columns_and_rows() {
+        // Tall columns and wide rows in a lattice, with one claim per gap
+        let mut claims = vec![];
+        for i in 0..12 {
+            claims.push(format!("#{} @ {},0: 2x100", claims.len() + 1, i * 8));
+            claims.push(format!("#{} @ 0,{}: 100x2", claims.len() + 1, i * 8 + 3));
+            claims.push(format!(
+                "#{} @ {},{}: 3x3",
+                claims.len() + 1,
+                i * 8 + 3,
+                i * 8
+            ));
+        }
+        claims.push("#37 @ 2,0: 1x3".to_string());
+        claims.push("#38 @ 200,200: 5x5".to_string());
+        let claims = input_claims(&claims.join("\n")).unwrap();
+
+        let mut expected = vec![false; claims.len()];
+        for (i, j) in overlapping_pairs(&claims) {
+            expected[i] = true;
+            expected[j] = true;
+        }
+        assert_eq!(overlapped_claims(&claims), expected);
+        assert_eq!(clean_claims(&claims).last(), Some(&38));
+    }
+
+    #[test]
+    fn all_overlapping() {
+        assert_eq!(
+            part2(&input_claims("#1 @ 1,3: 4x4\n#2 @ 3,1: 4x4").unwrap()),
+            Err(NoAnswer::new(3, "no non-overlapping claim"))
+        );
     }
 }
@@ -3,6 +3,7 @@
 extern crate time;
 
 use crate::day4::time::Duration;
+use crate::parse_error::ParseError;
 use chrono::prelude::*;
 use lazy_static;
 use regex::Regex;
@@ -21,37 +22,46 @@ pub struct GuardEvent {
     event: EventType,
 }
 
-#[aoc_generator(day4)]
-pub fn input_schedule(input: &str) -> Vec<GuardEvent> {
-    let mut collected: Vec<&str> = input.lines().collect();
-    collected.sort();
-
+fn parse_event(text: &str) -> Option<GuardEvent> {
     lazy_static::lazy_static! {
-        static ref ScheduleRE: Regex =
+        static ref SCHEDULE_RE: Regex =
         Regex::new(r"^\[(.+)\] (.+)$").unwrap();
-        static ref GuardRE: Regex =
+        static ref GUARD_RE: Regex =
         Regex::new(r"^Guard #(\d+) begins shift$").unwrap();
     }
 
+    let s_cap = SCHEDULE_RE.captures(text.trim())?;
+    let dt = NaiveDateTime::parse_from_str(&s_cap[1], "%Y-%m-%d %H:%M").ok()?;
+    let event = match &s_cap[2] {
+        "falls asleep" => EventType::Asleep,
+        "wakes up" => EventType::Awake,
+        other => {
+            let guard_id: usize = GUARD_RE.captures(other)?[1].parse().ok()?;
+            EventType::StartedShift { id: guard_id }
+        }
+    };
+    Some(GuardEvent { dt, event })
+}
+
+// Records start with their timestamp, so sorting the lines puts them in
+// chronological order. Line numbers are taken before sorting, so that errors
+// point at the line as it appears in the input
+#[aoc_generator(day4)]
+pub fn input_schedule(input: &str) -> Result<Vec<GuardEvent>, ParseError> {
+    let mut collected: Vec<(usize, &str)> = (1..).zip(input.lines()).collect();
+    collected.sort_by_key(|&(_, text)| text);
+
     collected
         .iter()
-        .map(|event| {
-            let s_cap: Vec<&str> = ScheduleRE
-                .captures(event)
-                .unwrap()
-                .iter()
-                .map(|c| c.unwrap().as_str())
-                .collect();
-            let dt = NaiveDateTime::parse_from_str(s_cap[1], "%Y-%m-%d %H:%M").unwrap();
-            let event = match s_cap[2] {
-                "falls asleep" => EventType::Asleep,
-                "wakes up" => EventType::Awake,
-                _ => {
-                    let guard_id: usize = GuardRE.captures(s_cap[2]).unwrap()[1].parse().unwrap();
-                    EventType::StartedShift { id: guard_id }
-                }
-            };
-            GuardEvent { dt, event }
+        .map(|&(line, text)| {
+            parse_event(text).ok_or_else(|| {
+                ParseError::new(
+                    4,
+                    line,
+                    text,
+                    "a record such as [1518-11-01 00:05] falls asleep",
+                )
+            })
         })
         .collect()
 }
@@ -67,10 +77,7 @@ fn order_schedule(schedule: &[GuardEvent]) -> HashMap<usize, Vec<GuardEvent>> {
         }
 
         if let Some(id) = current_guard {
-            mapped_schedule
-                .entry(id)
-                .or_insert_with(Vec::new)
-                .push(record.clone());
+            mapped_schedule.entry(id).or_default().push(record.clone());
         }
     }
     mapped_schedule
@@ -134,14 +141,14 @@ fn sleepiest_minute(schedule: &[GuardEvent]) -> Option<(usize, usize)> {
         .max_by_key(|&(_, count)| count)
         .map(|(val, _)| val)
     {
-        return Some((*sleepiest_minute, counter[&sleepiest_minute]));
+        return Some((*sleepiest_minute, counter[sleepiest_minute]));
     }
 
     None
 }
 
 #[aoc(day4, part1)]
-pub fn part1(input: &Vec<GuardEvent>) -> usize {
+pub fn part1(input: &[GuardEvent]) -> usize {
     let schedule = order_schedule(input);
     let (guard, _) = sleepiest_guard(&schedule);
 
@@ -149,7 +156,7 @@ pub fn part1(input: &Vec<GuardEvent>) -> usize {
 }
 
 #[aoc(day4, part2)]
-pub fn part2(input: &Vec<GuardEvent>) -> usize {
+pub fn part2(input: &[GuardEvent]) -> usize {
     let schedule = order_schedule(input);
     let mut max_freq = 0;
     let mut most_freq_asleep_guard: Option<usize> = None;
@@ -263,12 +270,38 @@ mod tests {
             },
         ];
 
-        assert_eq!(input_schedule(TEST_STR), expected);
+        assert_eq!(input_schedule(TEST_STR), Ok(expected));
+    }
+
+    #[test]
+    fn malformed() {
+        let expected = "a record such as [1518-11-01 00:05] falls asleep";
+
+        assert_eq!(
+            input_schedule(
+                "[1518-11-01 00:05] falls asleep\n[1518-11-01 00:00] Guard #x begins shift"
+            ),
+            Err(ParseError::new(
+                4,
+                2,
+                "[1518-11-01 00:00] Guard #x begins shift",
+                expected
+            ))
+        );
+        assert_eq!(
+            input_schedule("[1518-11-01 00:25] wakes up\n[1518-13-01 00:05] falls asleep"),
+            Err(ParseError::new(
+                4,
+                2,
+                "[1518-13-01 00:05] falls asleep",
+                expected
+            ))
+        );
     }
 
     #[test]
     fn sleepy() {
-        let schedule = order_schedule(&input_schedule(TEST_STR));
+        let schedule = order_schedule(&input_schedule(TEST_STR).unwrap());
         let (guard, time_asleep) = sleepiest_guard(&schedule);
         let (minute, freq) = sleepiest_minute(&schedule[&guard]).unwrap();
 
@@ -278,11 +311,11 @@ mod tests {
 
     #[test]
     fn sample1() {
-        assert_eq!(part1(&input_schedule(TEST_STR)), 240)
+        assert_eq!(part1(&input_schedule(TEST_STR).unwrap()), 240)
     }
 
     #[test]
     fn sample2() {
-        assert_eq!(part2(&input_schedule(TEST_STR)), 4455)
+        assert_eq!(part2(&input_schedule(TEST_STR).unwrap()), 4455)
     }
 }
@@ -1,55 +1,312 @@
-pub fn react_polymer(polymer: &str) -> usize {
-    let search_iter = (97..123)
-        .map(|x: u8| {
-            let c = x as char;
-            format!("{}{}", c , c.to_uppercase())
-        })
-        .chain((97..123).map(|x: u8| {
-            let c = x as char;
-            format!("{}{}", c.to_uppercase(), c)
-        }));
-
-    let mut parsed: String = polymer.to_string();
-
-    loop {
-        let search_iter_clone = search_iter.clone();
-        let mut scanned = false; 
-
-        for search in search_iter_clone {
-            let temp = parsed.replace(search.as_str(), "");
-            if temp.len() < parsed.len() {
-                scanned = true;
-                parsed = temp;
+// Day 5: Alchemical Reduction //
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::thread;
+
+// The puzzle's rule: a letter reacts with the same letter in the opposite
+// case. The solvers reduce raw bytes with it, as the fast path of
+// `ReactionRules::default()`
+fn reacts(a: u8, b: u8) -> bool {
+    a != b && a.eq_ignore_ascii_case(&b)
+}
+
+// Single pass over the units, keeping the reduced polymer so far on a stack.
+// A unit that reacts with the top of the stack destroys it, which exposes the
+// unit below to whatever comes next, exactly as repeated scans would.
+// `on_reaction` sees every pair destroyed, in the order it happens
+fn reduce_with<T, I, F, G>(units: I, reacts: F, mut on_reaction: G) -> Vec<T>
+where
+    T: Copy,
+    I: IntoIterator<Item = T>,
+    F: Fn(T, T) -> bool,
+    G: FnMut(T, T),
+{
+    let mut stack = vec![];
+    for unit in units {
+        match stack.last() {
+            Some(&top) if reacts(top, unit) => {
+                stack.pop();
+                on_reaction(top, unit);
+            }
+            _ => stack.push(unit),
+        }
+    }
+    stack
+}
+
+fn reduce_by<T, I, F>(units: I, reacts: F) -> Vec<T>
+where
+    T: Copy,
+    I: IntoIterator<Item = T>,
+    F: Fn(T, T) -> bool,
+{
+    reduce_with(units, reacts, |_, _| {})
+}
+
+fn reduce<I: IntoIterator<Item = u8>>(units: I) -> Vec<u8> {
+    reduce_by(units, reacts)
+}
+
+// Which units annihilate each other. Units are single characters unless a
+// rule names a longer token, and the polymer is split greedily on the longest
+// token that fits. The default is the puzzle's rule, where a letter reacts
+// with the same letter in the opposite case
+#[derive(Debug, PartialEq, Clone)]
+pub struct ReactionRules {
+    reactions: HashMap<String, HashSet<String>>,
+    tokens: HashSet<String>,
+    longest: usize,
+}
+
+// Built from `reacts`, the rule the solvers apply directly to bytes, so that
+// both always agree
+impl Default for ReactionRules {
+    fn default() -> Self {
+        let letters: Vec<u8> = (b'a'..=b'z').chain(b'A'..=b'Z').collect();
+        let mut rules = ReactionRules::new();
+        for &a in &letters {
+            for &b in letters.iter().filter(|&&b| reacts(a, b)) {
+                rules = rules.with_pair(&(a as char).to_string(), &(b as char).to_string());
+            }
+        }
+        rules
+    }
+}
+
+impl ReactionRules {
+    // Rules under which nothing reacts
+    pub fn new() -> ReactionRules {
+        ReactionRules {
+            reactions: HashMap::new(),
+            tokens: HashSet::new(),
+            longest: 0,
+        }
+    }
+
+    // Makes `a` and `b` annihilate whenever they are adjacent, in either order
+    pub fn with_pair(mut self, a: &str, b: &str) -> ReactionRules {
+        for &(unit, other) in &[(a, b), (b, a)] {
+            self.reactions
+                .entry(unit.to_string())
+                .or_default()
+                .insert(other.to_string());
+            if unit.chars().count() > 1 {
+                self.tokens.insert(unit.to_string());
+                self.longest = self.longest.max(unit.len());
             }
         }
+        self
+    }
+
+    pub fn reacts(&self, a: &str, b: &str) -> bool {
+        self.reactions
+            .get(a)
+            .is_some_and(|partners| partners.contains(b))
+    }
+
+    // Splits the polymer into units, each with its byte offset in `polymer`
+    fn units<'a>(&self, polymer: &'a str) -> Vec<(usize, &'a str)> {
+        let mut units = vec![];
+        let mut offset = 0;
+        while let Some(first) = polymer[offset..].chars().next() {
+            let rest = &polymer[offset..];
+            let len = (2..=self.longest.min(rest.len()))
+                .rev()
+                .filter(|&len| rest.is_char_boundary(len))
+                .find(|&len| self.tokens.contains(&rest[..len]))
+                .unwrap_or_else(|| first.len_utf8());
+            units.push((offset, &rest[..len]));
+            offset += len;
+        }
+        units
+    }
+
+    // The polymer left once every possible reaction has happened
+    pub fn residual(&self, polymer: &str) -> String {
+        reduce_by(
+            self.units(polymer.trim()).into_iter().map(|(_, unit)| unit),
+            |a, b| self.reacts(a, b),
+        )
+        .concat()
+    }
 
-        if !scanned {
-            break;
+    // Reduces the polymer while recording every reaction along the way.
+    // Surrounding whitespace is skipped, but positions are still reported as
+    // byte offsets into `polymer` as given
+    pub fn trace(&self, polymer: &str) -> Trace {
+        let skipped = polymer.len() - polymer.trim_start().len();
+        let units: Vec<(usize, &str)> = self
+            .units(polymer.trim())
+            .into_iter()
+            .map(|(offset, unit)| (skipped + offset, unit))
+            .collect();
+
+        let mut events = vec![];
+        let mut reactions = vec![];
+        let left_over = reduce_with(
+            0..units.len(),
+            |a, b| self.reacts(units[a].1, units[b].1),
+            |a, b| {
+                reactions.push((a, b));
+                events.push(Annihilation {
+                    step: events.len() + 1,
+                    left: units[a].0,
+                    right: units[b].0,
+                    pair: (units[a].1.to_string(), units[b].1.to_string()),
+                })
+            },
+        );
+
+        Trace {
+            residual: left_over.iter().map(|&i| units[i].1).collect(),
+            units: units
+                .into_iter()
+                .map(|(_, unit)| unit.to_string())
+                .collect(),
+            reactions,
+            events,
         }
+    }
+}
+
+// One reaction, destroying the units starting at byte offsets `left` and
+// `right` of the polymer as given. Steps count reactions from 1
+#[derive(Debug, PartialEq, Clone)]
+pub struct Annihilation {
+    pub step: usize,
+    pub left: usize,
+    pub right: usize,
+    pub pair: (String, String),
+}
+
+impl fmt::Display for Annihilation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: {}{} at {} and {}",
+            self.step, self.pair.0, self.pair.1, self.left, self.right
+        )
+    }
+}
+
+// Every reaction of a polymer in the order they happen, which is enough to
+// replay the reduction one step at a time. `reactions` holds the indices of
+// the units each event destroyed
+#[derive(Debug, PartialEq, Clone)]
+pub struct Trace {
+    units: Vec<String>,
+    reactions: Vec<(usize, usize)>,
+    events: Vec<Annihilation>,
+    residual: String,
+}
 
+impl Trace {
+    pub fn events(&self) -> &[Annihilation] {
+        &self.events
     }
-    parsed.len()
+
+    pub fn residual(&self) -> &str {
+        &self.residual
+    }
+
+    // The polymer after each step in turn. Every step rebuilds the whole
+    // polymer, so this is meant for watching short reactions
+    pub fn replay(&self) -> impl Iterator<Item = String> + '_ {
+        let mut alive = vec![true; self.units.len()];
+        self.reactions.iter().map(move |&(left, right)| {
+            alive[left] = false;
+            alive[right] = false;
+            self.units
+                .iter()
+                .zip(&alive)
+                .filter(|(_, &alive)| alive)
+                .map(|(unit, _)| unit.as_str())
+                .collect()
+        })
+    }
+
+    // One line per reaction, ending with what is left
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for event in &self.events {
+            text += &format!("{}\n", event);
+        }
+        text + &format!("left: {}\n", self.residual)
+    }
+}
+
+pub fn react_polymer(polymer: &str) -> usize {
+    reduce(polymer.trim().bytes()).len()
 }
 
 #[aoc(day5, part1)]
 pub fn part1(input: &str) -> usize {
-    println!("{}", b'a' );
     react_polymer(input)
 }
 
-#[aoc(day5, part2)]
-pub fn part2(input: &str) -> usize {
-    let remove_iter = (97..123).map(|x: u8| {x as char});
+// What taking one unit type out of the fully reacted polymer does: `removed`
+// units go directly, `reacted` more are destroyed by the reactions that
+// become possible, and `length` units are left
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Removal {
+    pub unit: char,
+    pub removed: usize,
+    pub reacted: usize,
+    pub length: usize,
+}
 
-    let mut min = input.len();
-    for removed in remove_iter {
-        let scrubbed = input.replace(removed, "").replace(removed.to_ascii_uppercase(), "");
-        if react_polymer(&scrubbed) < min {
-            min = react_polymer(&scrubbed);
-        };
+impl fmt::Display for Removal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} removed, {} reacted, {} left",
+            self.unit, self.removed, self.reacted, self.length
+        )
     }
+}
+
+// Removing a unit type from the polymer and reducing gives the same result as
+// removing it from the already reduced polymer, which is much shorter. The 26
+// candidates are independent, so each one runs on its own thread
+pub fn removal_breakdown(polymer: &str) -> Vec<Removal> {
+    let reduced = reduce(polymer.trim().bytes());
+
+    thread::scope(|scope| {
+        let removals: Vec<_> = (b'a'..=b'z')
+            .map(|unit| {
+                let reduced = &reduced;
+                scope.spawn(move || {
+                    let kept: Vec<u8> = reduced
+                        .iter()
+                        .cloned()
+                        .filter(|other| !other.eq_ignore_ascii_case(&unit))
+                        .collect();
+                    let length = reduce(kept.iter().cloned()).len();
+                    Removal {
+                        unit: unit as char,
+                        removed: reduced.len() - kept.len(),
+                        reacted: kept.len() - length,
+                        length,
+                    }
+                })
+            })
+            .collect();
 
-    min
+        removals
+            .into_iter()
+            .map(|removal| removal.join().unwrap())
+            .collect()
+    })
+}
+
+#[aoc(day5, part2)]
+pub fn part2(input: &str) -> usize {
+    removal_breakdown(input)
+        .iter()
+        .map(|removal| removal.length)
+        .min()
+        .unwrap_or(0)
 }
 
 #[cfg(test)]
@@ -58,11 +315,93 @@ mod tests {
 
     #[test]
     fn sample1() {
-        assert_eq!(part1(&"dabAcCaCBAcCcaDA"), 10);
+        assert_eq!(part1("dabAcCaCBAcCcaDA"), 10);
     }
 
     #[test]
     fn sample2() {
-        assert_eq!(part2(&"dabAcCaCBAcCcaDA"), 4);
+        assert_eq!(part2("dabAcCaCBAcCcaDA"), 4);
+    }
+
+    #[test]
+    fn chain_reaction() {
+        assert_eq!(react_polymer("aA"), 0);
+        assert_eq!(react_polymer("abBA"), 0);
+        assert_eq!(react_polymer("abAB"), 4);
+        assert_eq!(react_polymer("aabAAB"), 6);
+        assert_eq!(react_polymer("dabAcCaCBAcCcaDA\n"), 10);
+    }
+
+    #[test]
+    fn trace() {
+        let trace = ReactionRules::default().trace("dabAcCaCBAcCcaDA");
+
+        assert_eq!(
+            trace.to_text(),
+            "1: cC at 4 and 5\n\
+             2: Aa at 3 and 6\n\
+             3: cC at 10 and 11\n\
+             left: dabCBAcaDA\n"
+        );
+        assert_eq!(
+            trace.replay().collect::<Vec<_>>(),
+            vec!["dabAaCBAcCcaDA", "dabCBAcCcaDA", "dabCBAcaDA"]
+        );
+        assert_eq!(ReactionRules::default().trace("abc").residual(), "abc");
+
+        let padded = ReactionRules::default().trace("  xaA\n");
+        assert_eq!(padded.events()[0].left, 3);
+        assert_eq!(padded.events()[0].right, 4);
+        assert_eq!(padded.residual(), "x");
+
+        let tokens = ReactionRules::new()
+            .with_pair("up", "down")
+            .trace("xéupdown");
+        assert_eq!(tokens.to_text(), "1: updown at 3 and 5\nleft: xé\n");
+    }
+
+    #[test]
+    fn breakdown() {
+        let removals = removal_breakdown("dabAcCaCBAcCcaDA");
+
+        assert_eq!(removals.len(), 26);
+        assert_eq!(
+            removals[0],
+            Removal {
+                unit: 'a',
+                removed: 4,
+                reacted: 0,
+                length: 6,
+            }
+        );
+        assert_eq!(removals[2].to_string(), "c: 2 removed, 4 reacted, 4 left");
+        assert_eq!(removals[25].length, 10);
+    }
+
+    #[test]
+    fn default_rules_match_solvers() {
+        let rules = ReactionRules::default();
+        for polymer in &["dabAcCaCBAcCcaDA", include_str!("../input/2018/day5.txt")] {
+            assert_eq!(rules.residual(polymer).len(), react_polymer(polymer));
+        }
+    }
+
+    #[test]
+    fn custom_rules() {
+        assert_eq!(
+            ReactionRules::default().residual("dabAcCaCBAcCcaDA"),
+            "dabCBAcaDA"
+        );
+        assert_eq!(ReactionRules::new().residual("aA"), "aA");
+
+        let bases = ReactionRules::new().with_pair("A", "T").with_pair("C", "G");
+        assert_eq!(bases.residual("GATTACA"), "A");
+
+        let tokens = ReactionRules::new()
+            .with_pair("up", "down")
+            .with_pair("α", "ω");
+        assert_eq!(tokens.residual("uupdownpαω"), "up");
+        assert!(tokens.reacts("down", "up"));
+        assert!(!tokens.reacts("up", "up"));
     }
 }
@@ -1,189 +1,745 @@
 // Day 6: Chronal Coordinates //
+use crate::no_answer::NoAnswer;
+use crate::parse_error::{parse_lines, ParseError};
 use lazy_static;
 use regex::Regex;
-use std::collections::HashMap;
+use std::collections::VecDeque;
 
+#[derive(Debug, PartialEq)]
 pub struct Marker {
     id: usize,
     x: usize,
     y: usize,
 }
 
-pub struct Point {
-    is_marker: bool,
-    is_edge: bool,
-    closest_to: Option<usize>,
-    total_distance: bool,
+impl Marker {
+    fn distance<M: Metric>(&self, x: isize, y: isize, metric: &M) -> f64 {
+        metric.distance(
+            (self.x as isize - x).unsigned_abs(),
+            (self.y as isize - y).unsigned_abs(),
+        )
+    }
 }
 
-pub struct Grid {
-    points: Vec<Point>,
+// A way of measuring how far apart two locations are, given how far apart
+// they are along each axis. Distances must not shrink as either offset grows,
+// must scale with the offsets, and must be positive for any offset other than
+// 0, 0, so that a safe region can be bounded.
+//
+// No one test tells finite regions from infinite ones under every such metric,
+// so each metric measures the areas itself, on a grid whose cells lie
+// `scale.0` apart along x and `scale.1` apart along y
+pub trait Metric {
+    fn distance(&self, dx: usize, dy: usize) -> f64;
+
+    // The area of every marker's region by ID, or None where it is infinite
+    fn scaled_areas(&self, markers: &[Marker], scale: (usize, usize)) -> Vec<Option<usize>>;
 }
 
-impl Grid {
-    pub fn find_area(self) -> usize {
-        let mut dc_markers = vec![];
-        let mut areas = HashMap::new();
-        for point in self.points {
-            match point.closest_to {
-                None => {
-                    continue;
+// The taxicab distance of the puzzle
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Manhattan;
+
+impl Metric for Manhattan {
+    fn distance(&self, dx: usize, dy: usize) -> f64 {
+        (dx + dy) as f64
+    }
+
+    // Touching the edge of the bounding box makes a region infinite, as in
+    // `Voronoi::areas`, however the axes are scaled
+    fn scaled_areas(&self, markers: &[Marker], scale: (usize, usize)) -> Vec<Option<usize>> {
+        let (min_x, max_x, min_y, max_y) = match grid_coords_finder(markers) {
+            Some(bounds) => bounds,
+            None => return vec![],
+        };
+        let (min_x, max_x, min_y, max_y) = (
+            min_x as isize,
+            max_x as isize,
+            min_y as isize,
+            max_y as isize,
+        );
+
+        frontier_areas(
+            markers,
+            &Weighted {
+                metric: Manhattan,
+                x: scale.0,
+                y: scale.1,
+            },
+            (min_y..=max_y).flat_map(|y| (min_x..=max_x).map(move |x| (x, y))),
+            |x, y| x == min_x || x == max_x || y == min_y || y == max_y,
+        )
+    }
+}
+
+// Moves in any of the eight directions, diagonals included, cost the same
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Chebyshev;
+
+impl Metric for Chebyshev {
+    fn distance(&self, dx: usize, dy: usize) -> f64 {
+        dx.max(dy) as f64
+    }
+
+    // Chebyshev distance is half the Manhattan distance along the diagonals
+    // u = x + y and v = x - y, so the box around the markers in those
+    // coordinates plays the part of the bounding box. Past its edge, each
+    // diagonal step of `step` cells along both axes adds the same to the
+    // distance to every marker. So a cell further out is owned like the cell
+    // a whole number of steps back within one step of the edge, and only the
+    // cells up to one step past the edge need counting
+    fn scaled_areas(&self, markers: &[Marker], scale: (usize, usize)) -> Vec<Option<usize>> {
+        let (sx, sy) = (scale.0 as isize, scale.1 as isize);
+        let step = sx / gcd(sx, sy) * sy;
+        let u = |x: isize, y: isize| sx * x + sy * y;
+        let v = |x: isize, y: isize| sx * x - sy * y;
+
+        let (min_u, max_u, min_v, max_v) = match markers
+            .iter()
+            .map(|marker| {
+                (
+                    u(marker.x as isize, marker.y as isize),
+                    v(marker.x as isize, marker.y as isize),
+                )
+            })
+            .fold(None, |bounds, (u, v)| match bounds {
+                None => Some((u, u, v, v)),
+                Some((min_u, max_u, min_v, max_v)) => {
+                    Some((min_u.min(u), max_u.max(u), min_v.min(v), max_v.max(v)))
                 }
-                Some(marker_id) => {
-                    if point.is_edge {
-                        dc_markers.push(point.closest_to.unwrap())
-                    }
-                    if !point.is_marker {
-                        *areas.entry(marker_id).or_insert(1) += 1;
-                    }
+            }) {
+            Some(bounds) => bounds,
+            None => return vec![],
+        };
+        let (lo_u, hi_u) = (min_u - 2 * step, max_u + 2 * step);
+        let (lo_v, hi_v) = (min_v - 2 * step, max_v + 2 * step);
+
+        let xs = (lo_u + lo_v).div_euclid(2 * sx)..=(hi_u + hi_v).div_euclid(2 * sx) + 1;
+        let ys = (lo_u - hi_v).div_euclid(2 * sy)..=(hi_u - lo_v).div_euclid(2 * sy) + 1;
+        frontier_areas(
+            markers,
+            &Weighted {
+                metric: Chebyshev,
+                x: scale.0,
+                y: scale.1,
+            },
+            ys.flat_map(|y| xs.clone().map(move |x| (x, y)))
+                .filter(|&(x, y)| {
+                    lo_u < u(x, y) && u(x, y) < hi_u && lo_v < v(x, y) && v(x, y) < hi_v
+                }),
+            |x, y| u(x, y) <= min_u || u(x, y) >= max_u || v(x, y) <= min_v || v(x, y) >= max_v,
+        )
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Euclidean;
+
+impl Metric for Euclidean {
+    fn distance(&self, dx: usize, dy: usize) -> f64 {
+        ((dx * dx + dy * dy) as f64).sqrt()
+    }
+
+    // A region is infinite exactly when its marker lies on the convex hull of
+    // the markers, edges included: some direction then leads no closer to any
+    // other marker, and the marker stays the closest one all along it.
+    // Otherwise the region is the polygon cut out by the bisectors with the
+    // other markers, counted column by column in exact integer arithmetic
+    fn scaled_areas(&self, markers: &[Marker], scale: (usize, usize)) -> Vec<Option<usize>> {
+        let (sx, sy) = (scale.0 as i128, scale.1 as i128);
+        markers
+            .iter()
+            .map(|marker| {
+                let (px, py) = (marker.x as i128, marker.y as i128);
+                let offsets: Vec<(i128, i128)> = markers
+                    .iter()
+                    .filter(|other| other.id != marker.id)
+                    .map(|other| (other.x as i128 - px, other.y as i128 - py))
+                    .collect();
+                if offsets.contains(&(0, 0)) {
+                    return Some(0);
                 }
-            }
-        }
-        areas.iter().fold(0, |mut state, (&id, &area)| {
-            if !dc_markers.contains(&id) {
-                if state < area {
-                    state = area
+                let cross = |a: (i128, i128), b: (i128, i128)| a.0 * b.1 - a.1 * b.0;
+                let on_hull = offsets.is_empty()
+                    || offsets.iter().any(|&edge| {
+                        offsets.iter().all(|&offset| cross(edge, offset) >= 0)
+                            || offsets.iter().all(|&offset| cross(edge, offset) <= 0)
+                    });
+                if on_hull {
+                    return None;
                 }
-            }
-            state
-        })
+
+                // Closer to the marker than to the one at offset dx, dy, as
+                // a * x + b * y < c
+                let bisectors: Vec<(i128, i128, i128)> = offsets
+                    .iter()
+                    .map(|&(dx, dy)| {
+                        let (qx, qy) = (px + dx, py + dy);
+                        (
+                            2 * sx * sx * dx,
+                            2 * sy * sy * dy,
+                            sx * sx * (qx * qx - px * px) + sy * sy * (qy * qy - py * py),
+                        )
+                    })
+                    .collect();
+                Some(polygon_area(&bisectors))
+            })
+            .collect()
     }
 }
 
-pub struct PointBuilder {
+// Another metric with the axes stretched, for when moving along one of them
+// costs more than along the other
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Weighted<M> {
+    metric: M,
     x: usize,
     y: usize,
 }
 
-impl PointBuilder {
-    pub fn new(self, markers: &[Marker]) -> Point {
-        Point {
-            closest_to: self.closest_to(markers),
-            is_edge: self.is_edge(grid_coords_finder(markers)),
-            is_marker: self.is_marker(markers),
-            total_distance: self.total_distance(markers),
+impl<M> Weighted<M> {
+    // None if either weight is zero, which would make moving along that axis
+    // free and leave a safe region without bounds
+    pub fn new(metric: M, x: usize, y: usize) -> Option<Weighted<M>> {
+        if x == 0 || y == 0 {
+            None
+        } else {
+            Some(Weighted { metric, x, y })
         }
     }
-    pub fn closest_to(&self, markers: &[Marker]) -> Option<usize> {
-        let mut id_of_closest_marker: Option<usize> = None;
-        let mut smallest_distance: usize = 500;
+}
+
+impl<M: Metric> Metric for Weighted<M> {
+    fn distance(&self, dx: usize, dy: usize) -> f64 {
+        self.metric.distance(dx * self.x, dy * self.y)
+    }
+
+    fn scaled_areas(&self, markers: &[Marker], scale: (usize, usize)) -> Vec<Option<usize>> {
+        self.metric
+            .scaled_areas(markers, (scale.0 * self.x, scale.1 * self.y))
+    }
+}
+
+fn gcd(a: isize, b: isize) -> isize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+// (min_x, max_x, min_y, max_y)
+pub type Bounds = (usize, usize, usize, usize);
+
+//Finds min/max x and min/max y coords for the grid
+//Returns (min_x, max_x, min_y, max_y), or None without any markers
+fn grid_coords_finder(input: &[Marker]) -> Option<Bounds> {
+    let first = input.first()?;
+    Some(
+        input
+            .iter()
+            .fold((first.x, first.x, first.y, first.y), |state, marker| {
+                (
+                    state.0.min(marker.x),
+                    state.1.max(marker.x),
+                    state.2.min(marker.y),
+                    state.3.max(marker.y),
+                )
+            }),
+    )
+}
+
+// A cell of the bounding box, with its distance to the closest markers and
+// the one marker it belongs to, or None when several are equally close
+#[derive(Debug, PartialEq, Clone, Copy)]
+struct Cell {
+    distance: usize,
+    owner: Option<usize>,
+}
+
+// The bounding box of the markers, with every cell assigned to its closest
+// marker, or to None on a tie
+#[derive(Debug, PartialEq)]
+pub struct Voronoi {
+    bounds: Bounds,
+    markers: usize,
+    owners: Vec<Option<usize>>,
+}
+
+impl Voronoi {
+    // Multi-source breadth first search from all the markers at once. Every
+    // shortest path stays inside the box, so the search distance is the
+    // Manhattan distance, and a cell reached at the same distance from two
+    // different owners (or from a tie) is a tie itself
+    pub fn new(markers: &[Marker]) -> Option<Voronoi> {
+        let bounds = grid_coords_finder(markers)?;
+        let width = bounds.1 - bounds.0 + 1;
+        let height = bounds.3 - bounds.2 + 1;
+        let index = |x: usize, y: usize| (y - bounds.2) * width + (x - bounds.0);
+
+        let mut cells: Vec<Option<Cell>> = vec![None; width * height];
+        let mut queue = VecDeque::new();
         for marker in markers {
-            let manh_distance = ((self.x as i32 - marker.x as i32).abs()
-                + (self.y as i32 - marker.y as i32).abs()) as usize;
-            if smallest_distance > manh_distance {
-                id_of_closest_marker = Some(marker.id);
-                smallest_distance = ((self.x as i32 - marker.x as i32).abs()
-                    + (self.y as i32 - marker.y as i32).abs())
-                    as usize;
-            } else if smallest_distance == manh_distance {
-                id_of_closest_marker = None;
+            let cell = &mut cells[index(marker.x, marker.y)];
+            match cell {
+                Some(cell) => cell.owner = None,
+                None => {
+                    *cell = Some(Cell {
+                        distance: 0,
+                        owner: Some(marker.id),
+                    });
+                    queue.push_back((marker.x, marker.y));
+                }
             }
         }
-        id_of_closest_marker
-    }
 
-    pub fn is_edge(&self, coords: (usize, usize, usize, usize)) -> bool {
-        if self.x == coords.0 && self.x == coords.1 {
-            return true;
-        } else if self.y == coords.2 && self.y == coords.3 {
-            return true;
+        while let Some((x, y)) = queue.pop_front() {
+            let from = cells[index(x, y)].unwrap();
+            let neighbours = [
+                (x > bounds.0, x.wrapping_sub(1), y),
+                (x < bounds.1, x + 1, y),
+                (y > bounds.2, x, y.wrapping_sub(1)),
+                (y < bounds.3, x, y + 1),
+            ];
+            for &(inside, nx, ny) in &neighbours {
+                if !inside {
+                    continue;
+                }
+                let cell = &mut cells[index(nx, ny)];
+                match cell {
+                    None => {
+                        *cell = Some(Cell {
+                            distance: from.distance + 1,
+                            owner: from.owner,
+                        });
+                        queue.push_back((nx, ny));
+                    }
+                    Some(cell) if cell.distance == from.distance + 1 => {
+                        if cell.owner != from.owner {
+                            cell.owner = None;
+                        }
+                    }
+                    Some(_) => {}
+                }
+            }
         }
-        false
+
+        Some(Voronoi {
+            bounds,
+            markers: markers.len(),
+            owners: cells
+                .into_iter()
+                .map(|cell| cell.and_then(|cell| cell.owner))
+                .collect(),
+        })
     }
 
-    pub fn is_marker(&self, markers: &[Marker]) -> bool {
-        let mut result = false;
-        for marker in markers {
-            if self.x == marker.x && self.y == marker.y {
-                result = true
+    // The area of every marker's region by ID, or None where the region
+    // touches the edge of the box. Past the edge every step outwards adds
+    // the same to the Manhattan distance to each marker, so the owner of an
+    // edge cell owns every cell beyond it, and no other region leaves the box
+    pub fn areas(&self) -> Vec<Option<usize>> {
+        let (min_x, max_x, min_y, max_y) = self.bounds;
+        let width = max_x - min_x + 1;
+        let mut areas = vec![Some(0); self.markers];
+
+        for (i, owner) in self.owners.iter().enumerate() {
+            let owner = match *owner {
+                Some(owner) => owner,
+                None => continue,
+            };
+            let (x, y) = (min_x + i % width, min_y + i / width);
+            if x == min_x || x == max_x || y == min_y || y == max_y {
+                areas[owner] = None;
+            }
+            if let Some(area) = &mut areas[owner] {
+                *area += 1;
             }
         }
-        result
+        areas
     }
+}
 
-    pub fn total_distance(&self, markers: &[Marker]) -> bool {
-        let mut running_total = 0;
-        for marker in markers {
-            let manh_distance = ((self.x as i32 - marker.x as i32).abs()
-                + (self.y as i32 - marker.y as i32).abs()) as usize;
-            if running_total + manh_distance < 10000 {
-                running_total += manh_distance;
-            } else {
-                return false;
+fn parse_marker(text: &str) -> Option<(usize, usize)> {
+    lazy_static::lazy_static! {
+        static ref RE: Regex =
+        Regex::new(r"^(\d+), (\d+)$").unwrap();
+    };
+
+    let m_cap = RE.captures(text.trim())?;
+    Some((m_cap[1].parse().ok()?, m_cap[2].parse().ok()?))
+}
+
+// Markers are numbered from 0 in input order
+#[aoc_generator(day6)]
+pub fn input_marker(input: &str) -> Result<Vec<Marker>, ParseError> {
+    let coords = parse_lines(input, 6, "a coordinate such as 132, 308", parse_marker)?;
+    Ok(coords
+        .into_iter()
+        .enumerate()
+        .map(|(id, (x, y))| Marker { id, x, y })
+        .collect())
+}
+
+// The marker strictly closest to x, y, or None on a tie
+fn closest<M: Metric>(markers: &[Marker], x: isize, y: isize, metric: &M) -> Option<usize> {
+    let mut closest: Option<(f64, Option<usize>)> = None;
+    for marker in markers {
+        let distance = marker.distance(x, y, metric);
+        closest = match closest {
+            Some((smallest, _)) if smallest == distance => Some((smallest, None)),
+            Some((smallest, owner)) if smallest < distance => Some((smallest, owner)),
+            _ => Some((distance, Some(marker.id))),
+        };
+    }
+    closest.and_then(|(_, owner)| owner)
+}
+
+// Counts every region over the given cells, taking one that owns a frontier
+// cell for infinite
+fn frontier_areas<M: Metric>(
+    markers: &[Marker],
+    metric: &M,
+    cells: impl Iterator<Item = (isize, isize)>,
+    frontier: impl Fn(isize, isize) -> bool,
+) -> Vec<Option<usize>> {
+    let mut areas = vec![Some(0); markers.len()];
+    for (x, y) in cells {
+        if let Some(owner) = closest(markers, x, y, metric) {
+            if frontier(x, y) {
+                areas[owner] = None;
+            }
+            if let Some(area) = &mut areas[owner] {
+                *area += 1;
             }
         }
-        return true;
     }
+    areas
 }
 
-//Finds min/max x and min/max y coords for the grid
-//Returns (min_x, max_x, min_y, max_y)
-fn grid_coords_finder(input: &[Marker]) -> (usize, usize, usize, usize) {
-    input
-        .iter()
-        .map(|marker| (marker.x, marker.y))
-        .fold((132, 132, 308, 308), |mut state, item| {
-            if item.0 <= state.0 {
-                state.0 = item.0;
-            } else if item.0 >= state.1 {
-                state.1 = item.0;
+// Number of cells x, y with a * x + b * y < c for every (a, b, c), which must
+// describe a bounded polygon. Its corners give the columns it spans, and each
+// column is an interval
+fn polygon_area(sides: &[(i128, i128, i128)]) -> usize {
+    let mut columns: Option<(f64, f64)> = None;
+    for (i, &(a1, b1, c1)) in sides.iter().enumerate() {
+        for &(a2, b2, c2) in &sides[i + 1..] {
+            let det = a1 * b2 - a2 * b1;
+            if det == 0 {
+                continue;
             }
-            if item.1 <= state.2 {
-                state.2 = item.1;
-            } else if item.1 >= state.3 {
-                state.3 = item.1;
+            let (x, y) = (c1 * b2 - c2 * b1, a1 * c2 - a2 * c1);
+            let inside = sides.iter().all(|&(a, b, c)| {
+                let (lhs, rhs) = (a * x + b * y, c * det);
+                if det > 0 {
+                    lhs <= rhs
+                } else {
+                    lhs >= rhs
+                }
+            });
+            if inside {
+                let x = x as f64 / det as f64;
+                columns = Some(columns.map_or((x, x), |(lo, hi)| (lo.min(x), hi.max(x))));
+            }
+        }
+    }
+    let (lo, hi) = match columns {
+        Some((lo, hi)) => (lo.floor() as i128 - 1, hi.ceil() as i128 + 1),
+        None => return 0,
+    };
+
+    (lo..=hi)
+        .map(|x| {
+            let (mut bottom, mut top) = (i128::MIN, i128::MAX);
+            for &(a, b, c) in sides {
+                let rest = c - a * x;
+                if b > 0 {
+                    top = top.min((rest - 1).div_euclid(b));
+                } else if b < 0 {
+                    bottom = bottom.max((-rest).div_euclid(-b) + 1);
+                } else if rest <= 0 {
+                    return 0;
+                }
+            }
+            if bottom <= top {
+                (top - bottom + 1) as usize
+            } else {
+                0
             }
-            state
         })
+        .sum()
+}
+
+// The area of every marker's region by ID under any metric, or None where
+// the region is infinite
+pub fn metric_areas<M: Metric>(markers: &[Marker], metric: &M) -> Vec<Option<usize>> {
+    metric.scaled_areas(markers, (1, 1))
 }
 
-pub fn instantiate_points(markers: &[Marker]) -> Vec<Point> {
-    let coords = grid_coords_finder(markers);
-    let mut points: Vec<Point> = vec![];
-    for x in coords.0..=coords.1 {
-        for y in coords.2..=coords.3 {
-            let point = PointBuilder { x, y }.new(markers);
-            points.push(point);
+fn largest_finite_area(areas: Vec<Option<usize>>) -> Result<usize, NoAnswer> {
+    areas
+        .into_iter()
+        .flatten()
+        .max()
+        .ok_or_else(|| NoAnswer::new(6, "every area is infinite"))
+}
+
+#[aoc(day6, part1)]
+pub fn part1(input: &[Marker]) -> Result<usize, NoAnswer> {
+    largest_finite_area(Voronoi::new(input).map_or(vec![], |voronoi| voronoi.areas()))
+}
+
+pub fn part1_with_metric<M: Metric>(input: &[Marker], metric: &M) -> Result<usize, NoAnswer> {
+    largest_finite_area(metric_areas(input, metric))
+}
+
+// Sum of the distances along one axis from every position in lo..=hi to the
+// given coordinates. Moving one step right brings the position a step closer
+// to the coordinates ahead of it, and a step further from the rest
+fn axis_sums(mut coords: Vec<isize>, lo: isize, hi: isize) -> Vec<usize> {
+    coords.sort_unstable();
+    let mut sum: isize = coords.iter().map(|&c| (c - lo).abs()).sum();
+    let mut passed = coords.iter().filter(|&&c| c <= lo).count();
+    let mut sums = Vec::with_capacity((hi - lo + 1).max(0) as usize);
+
+    for x in lo..=hi {
+        sums.push(sum as usize);
+        while passed < coords.len() && coords[passed] <= x {
+            passed += 1;
         }
+        sum += passed as isize - (coords.len() - passed) as isize;
     }
-    points
+    sums
 }
 
-#[aoc_generator(day6)]
-pub fn input_marker(input: &str) -> Vec<Marker> {
-    lazy_static::lazy_static! {
-        static ref RE: Regex =
-        Regex::new(r"(\d+), (\d+)").unwrap();
+// Number of locations whose Manhattan distances to all the markers add up to
+// less than `threshold`. The total splits into a sum over the x coordinates
+// and one over the y coordinates, so it is enough to know those along each
+// axis. Past the bounding box every step adds one per marker to its axis sum,
+// so the region cannot reach further out than threshold / markers
+pub fn safe_region_size(markers: &[Marker], threshold: usize) -> usize {
+    let (min_x, max_x, min_y, max_y) = match grid_coords_finder(markers) {
+        Some(bounds) => bounds,
+        None => return 0,
     };
+    let margin = (threshold / markers.len()) as isize + 1;
+
+    let x_sums = axis_sums(
+        markers.iter().map(|marker| marker.x as isize).collect(),
+        min_x as isize - margin,
+        max_x as isize + margin,
+    );
+    let mut y_sums = axis_sums(
+        markers.iter().map(|marker| marker.y as isize).collect(),
+        min_y as isize - margin,
+        max_y as isize + margin,
+    );
+    y_sums.sort_unstable();
 
-    let collected: Vec<&str> = input.lines().collect();
-    collected
+    x_sums
         .iter()
-        .map(|l| {
-            let m_cap: Vec<&str> = RE
-                .captures(l)
-                .unwrap()
-                .iter()
-                .map(|c| c.unwrap().as_str())
-                .collect();
-            let id: usize = (m_cap[1].to_owned() + &m_cap[2].to_owned())
-                .parse()
-                .unwrap();
-            let x: usize = m_cap[1].parse().unwrap();
-            let y: usize = m_cap[2].parse().unwrap();
-            Marker { id, x, y }
-        })
-        .collect()
+        .filter(|&&x_sum| x_sum < threshold)
+        .map(|&x_sum| y_sums.partition_point(|&y_sum| y_sum < threshold - x_sum))
+        .sum()
 }
 
-#[aoc(day6, part1)]
-pub fn part1(input: &[Marker]) -> usize {
-    let points = instantiate_points(input);
-    Grid { points }.find_area()
+// The same under any metric, summing the distances cell by cell. A cell k
+// steps outside the bounding box is at least k steps from every marker along
+// one axis, which bounds how far out the region can reach
+pub fn safe_region_size_with_metric<M: Metric>(
+    markers: &[Marker],
+    threshold: f64,
+    metric: &M,
+) -> usize {
+    let (min_x, max_x, min_y, max_y) = match grid_coords_finder(markers) {
+        Some(bounds) => bounds,
+        None => return 0,
+    };
+    let step = metric.distance(1, 0).min(metric.distance(0, 1));
+    let margin = (threshold / (markers.len() as f64 * step)) as isize + 1;
+
+    let xs = min_x as isize - margin..=max_x as isize + margin;
+    let ys = min_y as isize - margin..=max_y as isize + margin;
+    xs.flat_map(|x| ys.clone().map(move |y| (x, y)))
+        .filter(|&(x, y)| {
+            markers
+                .iter()
+                .map(|marker| marker.distance(x, y, metric))
+                .sum::<f64>()
+                < threshold
+        })
+        .count()
 }
 
 #[aoc(day6, part2)]
 pub fn part2(input: &[Marker]) -> usize {
-    let points = instantiate_points(input);
-    points.iter().filter(|x| x.total_distance).count()
+    safe_region_size(input, 10000)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lcg::Lcg;
+
+    static TEST_STR: &str = "1, 1\n\
+                             1, 6\n\
+                             8, 3\n\
+                             3, 4\n\
+                             5, 5\n\
+                             8, 9";
+
+    #[test]
+    fn bounds() {
+        assert_eq!(
+            grid_coords_finder(&input_marker(TEST_STR).unwrap()),
+            Some((1, 8, 1, 9))
+        );
+        assert_eq!(
+            grid_coords_finder(&input_marker("400, 2\n3, 500").unwrap()),
+            Some((3, 400, 2, 500))
+        );
+        assert_eq!(grid_coords_finder(&[]), None);
+    }
+
+    #[test]
+    fn malformed() {
+        assert_eq!(
+            input_marker("1, 1\n1 6").err(),
+            Some(ParseError::new(
+                6,
+                2,
+                "1 6",
+                "a coordinate such as 132, 308"
+            ))
+        );
+    }
+
+    #[test]
+    fn sample1() {
+        assert_eq!(part1(&input_marker(TEST_STR).unwrap()), Ok(17));
+    }
+
+    #[test]
+    fn sequential_ids() {
+        assert_eq!(
+            input_marker("1, 23\n12, 3").unwrap(),
+            vec![Marker { id: 0, x: 1, y: 23 }, Marker { id: 1, x: 12, y: 3 }]
+        );
+    }
+
+    #[test]
+    fn infinite_areas() {
+        let voronoi = Voronoi::new(&input_marker(TEST_STR).unwrap()).unwrap();
+        assert_eq!(
+            voronoi.areas(),
+            vec![None, None, None, Some(9), Some(17), None]
+        );
+
+        let corners = input_marker("0, 0\n10, 0\n5, 5\n0, 10\n10, 10").unwrap();
+        assert_eq!(part1(&corners), Ok(41));
+
+        let column = input_marker("0, 0\n2, 0\n1, 4\n0, 8\n2, 8").unwrap();
+        assert_eq!(
+            part1(&column),
+            Err(NoAnswer::new(6, "every area is infinite"))
+        );
+    }
+
+    #[test]
+    fn metrics() {
+        let markers = input_marker(TEST_STR).unwrap();
+        assert_eq!(
+            metric_areas(&markers, &Manhattan),
+            Voronoi::new(&markers).unwrap().areas()
+        );
+        assert_eq!(
+            metric_areas(&markers, &Chebyshev),
+            vec![None, None, None, Some(8), Some(10), None]
+        );
+        assert_eq!(
+            metric_areas(&markers, &Euclidean),
+            vec![None, None, None, Some(10), Some(16), None]
+        );
+        assert_eq!(part1_with_metric(&markers, &Chebyshev), Ok(10));
+
+        let stretched = Weighted::new(Manhattan, 2, 1).unwrap();
+        assert_eq!(
+            part1_with_metric(&markers, &stretched),
+            Err(NoAnswer::new(6, "every area is infinite"))
+        );
+
+        // The third marker's region crosses the bottom of the box, but is
+        // closed off by the others further down
+        let hemmed_in = input_marker("0, 0\n10, 0\n5, 1\n5, 10").unwrap();
+        assert_eq!(
+            metric_areas(&hemmed_in, &Euclidean),
+            vec![None, None, Some(59), None]
+        );
+        assert_eq!(part1_with_metric(&hemmed_in, &Euclidean), Ok(59));
+
+        // Closed off only about 1250 cells below the box
+        let far_below = input_marker("0, 0\n100, 0\n50, 1\n50, 11").unwrap();
+        assert_eq!(
+            metric_areas(&far_below, &Euclidean),
+            vec![None, None, Some(31505), None]
+        );
+
+        assert_eq!(Weighted::new(Manhattan, 0, 1), None);
+        assert_eq!(Weighted::new(Euclidean, 3, 0), None);
+
+        assert_eq!(safe_region_size_with_metric(&markers, 20.0, &Chebyshev), 10);
+        assert_eq!(safe_region_size_with_metric(&markers, 25.0, &Euclidean), 20);
+        assert_eq!(safe_region_size_with_metric(&markers, 60.0, &stretched), 59);
+    }
+
+    #[test]
+    fn beyond_the_box() {
+        let single = input_marker("5, 5").unwrap();
+        assert_eq!(safe_region_size(&single, 3), 13);
+
+        let pair = input_marker("0, 0\n2, 0").unwrap();
+        assert_eq!(safe_region_size(&pair, 6), 11);
+        assert_eq!(safe_region_size_with_metric(&pair, 6.0, &Manhattan), 11);
+        assert_eq!(safe_region_size_with_metric(&pair, 6.0, &Chebyshev), 25);
+    }
+
+    // Checks the separable count against summing the distances cell by cell,
+    // on a spread of small generated marker sets
+    #[test]
+    fn separable_sums() {
+        let mut lcg = Lcg::new(2018);
+
+        for _ in 0..200 {
+            let markers: Vec<Marker> = (0..lcg.below(6) as usize + 1)
+                .map(|id| Marker {
+                    id,
+                    x: lcg.below(12) as usize,
+                    y: lcg.below(12) as usize,
+                })
+                .collect();
+            let threshold = lcg.below(60) as usize;
+
+            assert_eq!(
+                safe_region_size(&markers, threshold),
+                safe_region_size_with_metric(&markers, threshold as f64, &Manhattan),
+                "{:?} {}",
+                markers,
+                threshold
+            );
+        }
+    }
+
+    #[test]
+    fn shared_position() {
+        let markers = input_marker("0, 0\n4, 4\n2, 2\n2, 2").unwrap();
+        assert_eq!(
+            Voronoi::new(&markers).unwrap().areas(),
+            vec![None, None, Some(0), Some(0)]
+        );
+    }
+
+    #[test]
+    fn sample2() {
+        assert_eq!(safe_region_size(&input_marker(TEST_STR).unwrap(), 32), 16);
+    }
 }
@@ -1,98 +1,397 @@
-use itertools::iterate;
-use itertools::Itertools;
+use crate::dependency_graph::{CycleError, DependencyGraph};
+use crate::parse_error::{parse_lines, ParseError};
 use lazy_static;
 use regex::Regex;
 use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fmt;
 
-// Transfroms the input into a Vec of (Needed Step, Step)
-#[aoc_generator(day7)]
-pub fn input_steps(input: &str) -> Vec<(char, char)> {
+fn parse_step(text: &str) -> Option<(char, char)> {
     lazy_static::lazy_static! {
         static ref RE: Regex =
-        Regex::new(r"Step ([A-Z]) must be finished before step ([A-Z]) can begin\.").unwrap();
+        Regex::new(r"^Step ([A-Z]) must be finished before step ([A-Z]) can begin\.$").unwrap();
     };
 
-    let collected: Vec<&str> = input.lines().collect();
-    collected
-        .iter()
-        .map(|l| {
-            let cap: Vec<char> = RE
-                .captures(l)
-                .unwrap()
-                .iter()
-                .map(|c| c.unwrap().as_str().chars().next().unwrap())
-                .collect();
-            (cap[1], cap[2])
-        })
-        .collect()
+    let cap = RE.captures(text.trim())?;
+    Some((cap[1].chars().next()?, cap[2].chars().next()?))
+}
+
+// Transfroms the input into a Vec of (Needed Step, Step)
+#[aoc_generator(day7)]
+pub fn input_steps(input: &str) -> Result<Vec<(char, char)>, ParseError> {
+    parse_lines(
+        input,
+        7,
+        "a line such as Step C must be finished before step A can begin.",
+        parse_step,
+    )
 }
 
-//  Vec<(Needed Step, Step)>
-//  HashMap<Step ID, Vec(Needed Steps)>
 #[aoc(day7, part1)]
-pub fn part1(input: &Vec<(char, char)>) -> String {
-    let mut steps: BTreeMap<char, Vec<char>> = BTreeMap::new();
-    for step in input {
-        if !steps.contains_key(&step.1) {
-            steps.insert(step.1, vec![]);
-        }
-        if !steps.contains_key(&step.0) {
-            steps.insert(step.0, vec![step.1]);
-        } else {
-            steps.get_mut(&step.0).unwrap().push(step.1);
-        };
+pub fn part1(input: &[(char, char)]) -> Result<String, CycleError<char>> {
+    let graph: DependencyGraph<char> = input.iter().cloned().collect();
+    Ok(graph.order()?.into_iter().collect())
+}
+
+// A step as carried out by the worker simulation, with the seconds it started
+// and finished on
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct StepRun<T> {
+    step: T,
+    worker: usize,
+    start: usize,
+    finish: usize,
+}
+
+// The outcome of the worker simulation, with the runs in completion order
+#[derive(Debug, PartialEq)]
+pub struct Schedule<T> {
+    workers: usize,
+    runs: Vec<StepRun<T>>,
+}
+
+impl<T> Schedule<T> {
+    pub fn total_time(&self) -> usize {
+        self.runs.iter().map(|run| run.finish).max().unwrap_or(0)
     }
-    let entries_vec: Vec<char> = steps.keys().map(|&entry| entry).collect();
-    let mut final_order_vec: Vec<char> = vec![];
-    let mut temp_matches: Vec<char> = vec![];
-    let mut ready: bool = false;
-
-    while !ready {
-        for (step_id, needed_steps_vec) in steps.iter() {
-            if needed_steps_vec.len() == 0 && !final_order_vec.contains(step_id) {
-                final_order_vec.push(*step_id);
-            } else if needed_steps_vec.len() == final_order_vec.len()
-                && needed_steps_vec
+}
+
+impl<T: fmt::Display> Schedule<T> {
+    // Renders the second-by-second table from the puzzle text, showing what
+    // each worker is busy with and which steps are done by then
+    pub fn timeline(&self) -> String {
+        let mut table = String::from("Second");
+        for worker in 1..=self.workers {
+            table += &format!("  Worker {}", worker);
+        }
+        table += "  Done";
+
+        for second in 0..=self.total_time() {
+            let mut row = format!("\n{:<6}", second);
+            for worker in 0..self.workers {
+                let busy_with = self
+                    .runs
                     .iter()
-                    .all(|step| final_order_vec.contains(step))
-            {
-                temp_matches.push(*step_id)
+                    .find(|run| run.worker == worker && run.start <= second && second < run.finish)
+                    .map_or(".".to_string(), |run| run.step.to_string());
+                row += &format!("  {:<8}", busy_with);
             }
+            let done: String = self
+                .runs
+                .iter()
+                .filter(|run| run.finish <= second)
+                .map(|run| run.step.to_string())
+                .collect();
+            row += &format!("  {}", done);
+            table += row.trim_end();
         }
-        let sorted_temp: BTreeSet<char> = temp_matches.drain(..).collect();
-        final_order_vec.extend(sorted_temp);
+        table
+    }
+}
+
+// Returned when the steps cannot be scheduled: they form a cycle, there is
+// no duration for one of them, or there are no workers to run them
+#[derive(Debug, PartialEq, Clone)]
+pub enum ScheduleError<T> {
+    Cycle(CycleError<T>),
+    NoDuration(T),
+    NoWorkers,
+}
+
+impl<T> From<CycleError<T>> for ScheduleError<T> {
+    fn from(error: CycleError<T>) -> Self {
+        ScheduleError::Cycle(error)
+    }
+}
 
-        if final_order_vec.len() == entries_vec.len() {
-            ready = true
+impl<T: fmt::Display> fmt::Display for ScheduleError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScheduleError::Cycle(error) => write!(f, "{}", error),
+            ScheduleError::NoDuration(step) => write!(f, "no duration for step {}", step),
+            ScheduleError::NoWorkers => write!(f, "no workers to run the steps"),
         }
     }
-    // while !final_order_vec.len() == entries_vec.len() {
-    //     let modified_order = steps.iter().scan((final_order_vec, temp_matches), |(final_order_vec, temp_matches), step| {
-    //         for needed_step in step.1 {
-    //             match step.1.len() {
-    //             0 => temp_matches.push(*step.0),
-    //             x => {
-    //                 if x == final_order_vec.len() {
-    //                     if step.1.clone().iter().all(|x| final_order_vec.contains(&x)) {
-    //                         temp_matches.push(*step.0);
-    //                     }
-    //                 }
-    //             }
-    //         }
-    //         }
-
-    //         temp_matches
-    //     final_order_vec
-    //         .iter()
-    //         .chain(modified_order.drain(..).collect::<Vec<char>>().sort());
-    //     });
-    println!("{:#?}", &temp_matches);
-
-    String::from_utf8(
-        final_order_vec
+}
+
+impl<T: fmt::Debug + fmt::Display> Error for ScheduleError<T> {}
+
+// Notes for the graph exporters, giving the seconds each step was worked on
+pub fn schedule_notes<T: Ord + Clone>(schedule: &Schedule<T>) -> BTreeMap<T, String> {
+    schedule
+        .runs
+        .iter()
+        .map(|run| (run.step.clone(), format!("{}s-{}s", run.start, run.finish)))
+        .collect()
+}
+
+// Notes for the graph exporters, giving each step's position in the order
+pub fn order_notes(input: &[(char, char)]) -> Result<BTreeMap<char, String>, CycleError<char>> {
+    let graph: DependencyGraph<char> = input.iter().cloned().collect();
+    Ok(graph
+        .order()?
+        .into_iter()
+        .zip(1..)
+        .map(|(step, position)| (step, format!("#{}", position)))
+        .collect())
+}
+
+// The puzzle's durations: `base_time` plus 1 for A up to 26 for Z. Any other
+// step has none
+pub fn step_duration(step: char, base_time: usize) -> Option<usize> {
+    match step {
+        'A'..='Z' => Some(base_time + (step as u8 - b'A') as usize + 1),
+        _ => None,
+    }
+}
+
+// Runs the steps on a pool of workers. Whenever workers are idle they pick up
+// the available steps in order, and time then jumps straight to the next
+// moment a step is finished. `duration` gives how long each step takes
+pub fn schedule_steps<T, F>(
+    graph: &DependencyGraph<T>,
+    workers: usize,
+    duration: F,
+) -> Result<Schedule<T>, ScheduleError<T>>
+where
+    T: Ord + Clone,
+    F: Fn(&T) -> Option<usize>,
+{
+    if workers == 0 {
+        return Err(ScheduleError::NoWorkers);
+    }
+    graph.order()?;
+    let durations: BTreeMap<T, usize> = graph
+        .steps()
+        .map(|step| {
+            duration(step)
+                .map(|time| (step.clone(), time))
+                .ok_or_else(|| ScheduleError::NoDuration(step.clone()))
+        })
+        .collect::<Result<_, _>>()?;
+
+    let mut prerequisites: BTreeMap<T, BTreeSet<T>> = graph
+        .steps()
+        .map(|step| (step.clone(), graph.prerequisites_of(step).unwrap().clone()))
+        .collect();
+
+    let mut in_progress: Vec<Option<StepRun<T>>> = vec![None; workers];
+    let mut runs = vec![];
+    let mut now = 0;
+
+    loop {
+        let mut available: Vec<T> = prerequisites
             .iter()
-            .map(|&character| character as u8)
-            .collect(),
-    )
-    .unwrap()
+            .filter(|(_, needed)| needed.is_empty())
+            .map(|(step, _)| step.clone())
+            .collect();
+        available.reverse();
+
+        for (worker, slot) in in_progress.iter_mut().enumerate() {
+            if slot.is_none() {
+                if let Some(step) = available.pop() {
+                    prerequisites.remove(&step);
+                    *slot = Some(StepRun {
+                        finish: now + durations[&step],
+                        step,
+                        worker,
+                        start: now,
+                    });
+                }
+            }
+        }
+
+        match in_progress.iter().flatten().map(|run| run.finish).min() {
+            Some(finish) => now = finish,
+            None => break,
+        }
+
+        let mut finished: Vec<StepRun<T>> = in_progress
+            .iter_mut()
+            .filter(|slot| slot.as_ref().is_some_and(|run| run.finish == now))
+            .filter_map(|slot| slot.take())
+            .collect();
+        finished.sort_by(|a, b| a.step.cmp(&b.step));
+
+        for run in finished {
+            for needed in prerequisites.values_mut() {
+                needed.remove(&run.step);
+            }
+            runs.push(run);
+        }
+    }
+
+    Ok(Schedule { workers, runs })
+}
+
+// Schedules the puzzle's lettered steps with the durations from the puzzle
+pub fn schedule_letters(
+    input: &[(char, char)],
+    workers: usize,
+    base_time: usize,
+) -> Result<Schedule<char>, ScheduleError<char>> {
+    let graph: DependencyGraph<char> = input.iter().cloned().collect();
+    schedule_steps(&graph, workers, |&step| step_duration(step, base_time))
+}
+
+#[aoc(day7, part2)]
+pub fn part2(input: &[(char, char)]) -> Result<usize, ScheduleError<char>> {
+    Ok(schedule_letters(input, 5, 60)?.total_time())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static TEST_STR: &str = "Step C must be finished before step A can begin.\n\
+                             Step C must be finished before step F can begin.\n\
+                             Step A must be finished before step B can begin.\n\
+                             Step A must be finished before step D can begin.\n\
+                             Step B must be finished before step E can begin.\n\
+                             Step D must be finished before step E can begin.\n\
+                             Step F must be finished before step E can begin.";
+
+    #[test]
+    fn grok_input() {
+        assert_eq!(
+            input_steps(TEST_STR).unwrap(),
+            vec![
+                ('C', 'A'),
+                ('C', 'F'),
+                ('A', 'B'),
+                ('A', 'D'),
+                ('B', 'E'),
+                ('D', 'E'),
+                ('F', 'E'),
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed() {
+        assert_eq!(
+            input_steps("Step C must be finished before step A can begin.\nStep C before F"),
+            Err(ParseError::new(
+                7,
+                2,
+                "Step C before F",
+                "a line such as Step C must be finished before step A can begin."
+            ))
+        );
+    }
+
+    #[test]
+    fn timeline() {
+        let expected = "Second  Worker 1  Worker 2  Done\n\
+                        0       C         .\n\
+                        1       C         .\n\
+                        2       C         .\n\
+                        3       A         F         C\n\
+                        4       B         F         CA\n\
+                        5       B         F         CA\n\
+                        6       D         F         CAB\n\
+                        7       D         F         CAB\n\
+                        8       D         F         CAB\n\
+                        9       D         .         CABF\n\
+                        10      E         .         CABFD\n\
+                        11      E         .         CABFD\n\
+                        12      E         .         CABFD\n\
+                        13      E         .         CABFD\n\
+                        14      E         .         CABFD\n\
+                        15      .         .         CABFDE";
+
+        assert_eq!(
+            schedule_letters(&input_steps(TEST_STR).unwrap(), 2, 0)
+                .unwrap()
+                .timeline(),
+            expected
+        );
+    }
+
+    #[test]
+    fn cycle() {
+        let steps = vec![('A', 'B'), ('B', 'C'), ('C', 'A')];
+        let expected = CycleError {
+            cycle: vec!['A', 'B', 'C'],
+        };
+
+        assert_eq!(part1(&steps), Err(expected.clone()));
+        assert_eq!(
+            schedule_letters(&steps, 2, 0),
+            Err(ScheduleError::Cycle(expected))
+        );
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(step_duration('A', 60), Some(61));
+        assert_eq!(step_duration('Z', 0), Some(26));
+        assert_eq!(step_duration('a', 60), None);
+        assert_eq!(step_duration('!', 60), None);
+        assert_eq!(
+            schedule_letters(&[('A', 'b')], 2, 0),
+            Err(ScheduleError::NoDuration('b'))
+        );
+        assert_eq!(
+            schedule_letters(&[('A', 'B')], 0, 0),
+            Err(ScheduleError::NoWorkers)
+        );
+
+        let graph: DependencyGraph<&str> = vec![("fetch", "build"), ("configure", "build")]
+            .into_iter()
+            .collect();
+        let schedule = schedule_steps(&graph, 2, |step| Some(step.len())).unwrap();
+        assert_eq!(schedule.total_time(), 9 + 5);
+        assert_eq!(
+            schedule.timeline().lines().nth(6),
+            Some("5       configure  .         fetch")
+        );
+    }
+
+    #[test]
+    fn export() {
+        let steps = input_steps(TEST_STR).unwrap();
+        let graph: DependencyGraph<char> = steps.iter().cloned().collect();
+
+        assert_eq!(
+            graph.to_mermaid(&order_notes(&steps).unwrap()),
+            "flowchart TD\n\
+             \x20   s0[\"A<br/>#2\"]\n\
+             \x20   s1[\"B<br/>#3\"]\n\
+             \x20   s2[\"C<br/>#1\"]\n\
+             \x20   s3[\"D<br/>#4\"]\n\
+             \x20   s4[\"E<br/>#6\"]\n\
+             \x20   s5[\"F<br/>#5\"]\n\
+             \x20   s0 --> s1\n\
+             \x20   s0 --> s3\n\
+             \x20   s1 --> s4\n\
+             \x20   s2 --> s0\n\
+             \x20   s2 --> s5\n\
+             \x20   s3 --> s4\n\
+             \x20   s5 --> s4\n"
+        );
+
+        let schedule = schedule_letters(&steps, 2, 0).unwrap();
+        let dot = graph.to_dot(&schedule_notes(&schedule));
+        assert!(dot.contains("    \"C\" [label=\"C\\n0s-3s\"];\n"));
+        assert!(dot.contains("    \"E\" [label=\"E\\n10s-15s\"];\n"));
+        assert!(dot.contains("    \"C\" -> \"F\";\n"));
+    }
+
+    #[test]
+    fn sample1() {
+        assert_eq!(
+            part1(&input_steps(TEST_STR).unwrap()),
+            Ok("CABDFE".to_string())
+        );
+    }
+
+    #[test]
+    fn sample2() {
+        assert_eq!(
+            schedule_letters(&input_steps(TEST_STR).unwrap(), 2, 0)
+                .map(|schedule| schedule.total_time()),
+            Ok(15)
+        );
+    }
 }
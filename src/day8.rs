@@ -0,0 +1,209 @@
+// Day 8: Memory Maneuver //
+
+use crate::parse_error::ParseError;
+
+#[derive(Debug, PartialEq, Default)]
+pub struct Node {
+    children: Vec<Node>,
+    metadata: Vec<usize>,
+}
+
+solver_input!(Node);
+
+// Dropping a deep tree field by field would recurse once per level, so the
+// children are flattened onto a work list and dropped one at a time instead
+impl Drop for Node {
+    fn drop(&mut self) {
+        let mut pending: Vec<Node> = std::mem::take(&mut self.children);
+        while let Some(mut node) = pending.pop() {
+            pending.append(&mut node.children);
+        }
+    }
+}
+
+// A node whose header has been read but whose children are still being parsed
+struct Frame {
+    children_left: usize,
+    metadata_len: usize,
+    node: Node,
+}
+
+const EXPECTED: &str = "a tree written as space-separated numbers";
+
+// Hands out the numbers of the license file one at a time, keeping track of
+// the line each one came from for error reporting
+struct Numbers<'a> {
+    tokens: Vec<(usize, &'a str)>,
+    next: usize,
+}
+
+impl<'a> Numbers<'a> {
+    fn new(input: &'a str) -> Numbers<'a> {
+        let tokens = input
+            .lines()
+            .zip(1..)
+            .flat_map(|(text, line)| text.split_whitespace().map(move |token| (line, token)))
+            .collect();
+        Numbers { tokens, next: 0 }
+    }
+
+    fn next_number(&mut self) -> Result<usize, ParseError> {
+        match self.tokens.get(self.next) {
+            Some(&(line, text)) => {
+                self.next += 1;
+                text.parse()
+                    .map_err(|_| ParseError::new(8, line, text, EXPECTED))
+            }
+            None => {
+                let line = self.tokens.last().map_or(1, |&(line, _)| line);
+                Err(ParseError::new(8, line, "", EXPECTED))
+            }
+        }
+    }
+
+    fn read_header(&mut self) -> Result<Frame, ParseError> {
+        Ok(Frame {
+            children_left: self.next_number()?,
+            metadata_len: self.next_number()?,
+            node: Node::default(),
+        })
+    }
+}
+
+// Builds the tree with an explicit stack of partially parsed nodes, so the
+// depth of the license file is bounded by memory rather than the call stack
+#[aoc_generator(day8)]
+pub fn input_tree(input: &str) -> Result<Node, ParseError> {
+    let mut numbers = Numbers::new(input);
+
+    let mut stack = vec![numbers.read_header()?];
+    let root = loop {
+        let frame = stack.last_mut().unwrap();
+        if frame.children_left > 0 {
+            frame.children_left -= 1;
+            let child = numbers.read_header()?;
+            stack.push(child);
+            continue;
+        }
+
+        let mut frame = stack.pop().unwrap();
+        frame.node.metadata = (0..frame.metadata_len)
+            .map(|_| numbers.next_number())
+            .collect::<Result<_, _>>()?;
+
+        match stack.last_mut() {
+            Some(parent) => parent.node.children.push(frame.node),
+            None => break frame.node,
+        }
+    };
+
+    // Anything after the root node is not part of the tree
+    match numbers.tokens.get(numbers.next) {
+        Some(&(line, text)) => Err(ParseError::new(8, line, text, EXPECTED)),
+        None => Ok(root),
+    }
+}
+
+#[aoc(day8, part1)]
+pub fn part1(input: &Node) -> usize {
+    let mut total = 0;
+    let mut to_visit = vec![input];
+    while let Some(node) = to_visit.pop() {
+        total += node.metadata.iter().sum::<usize>();
+        to_visit.extend(node.children.iter());
+    }
+    total
+}
+
+// The value of a node depends on the values of its children, so nodes are
+// visited twice: once to queue up the children, and once more to combine them
+#[aoc(day8, part2)]
+pub fn part2(input: &Node) -> usize {
+    let mut values: Vec<usize> = vec![];
+    let mut to_visit = vec![(input, false)];
+
+    while let Some((node, children_done)) = to_visit.pop() {
+        if node.children.is_empty() {
+            values.push(node.metadata.iter().sum());
+        } else if !children_done {
+            to_visit.push((node, true));
+            to_visit.extend(node.children.iter().rev().map(|child| (child, false)));
+        } else {
+            let children_values = values.split_off(values.len() - node.children.len());
+            let value = node
+                .metadata
+                .iter()
+                .filter_map(|&index| index.checked_sub(1))
+                .filter_map(|index| children_values.get(index))
+                .sum();
+            values.push(value);
+        }
+    }
+
+    values.pop().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static TEST_STR: &str = "2 3 0 3 10 11 12 1 1 0 1 99 2 1 1 2";
+
+    #[test]
+    fn grok_input() {
+        let expected = Node {
+            children: vec![
+                Node {
+                    children: vec![],
+                    metadata: vec![10, 11, 12],
+                },
+                Node {
+                    children: vec![Node {
+                        children: vec![],
+                        metadata: vec![99],
+                    }],
+                    metadata: vec![2],
+                },
+            ],
+            metadata: vec![1, 1, 2],
+        };
+
+        assert_eq!(input_tree(TEST_STR), Ok(expected));
+    }
+
+    #[test]
+    fn malformed() {
+        assert_eq!(
+            input_tree("2 3 0 3 10 11 12\n1 1 0 x 99 2 1 1 2"),
+            Err(ParseError::new(8, 2, "x", EXPECTED))
+        );
+        assert_eq!(
+            input_tree("2 3 0 3 10 11 12 1 1 0 1 99 2 1 1"),
+            Err(ParseError::new(8, 1, "", EXPECTED))
+        );
+        assert_eq!(
+            input_tree("0 1 5\n7"),
+            Err(ParseError::new(8, 2, "7", EXPECTED))
+        );
+    }
+
+    #[test]
+    fn deep_tree() {
+        let depth = 200_000;
+        let input = "1 1 ".repeat(depth) + "0 1 7" + &" 1".repeat(depth);
+        let tree = input_tree(&input).unwrap();
+
+        assert_eq!(part1(&tree), depth + 7);
+        assert_eq!(part2(&tree), 7);
+    }
+
+    #[test]
+    fn sample1() {
+        assert_eq!(part1(&input_tree(TEST_STR).unwrap()), 138);
+    }
+
+    #[test]
+    fn sample2() {
+        assert_eq!(part2(&input_tree(TEST_STR).unwrap()), 66);
+    }
+}
@@ -0,0 +1,124 @@
+// Day 9: Marble Mania //
+
+use crate::parse_error::ParseError;
+use lazy_static;
+use regex::Regex;
+use std::collections::VecDeque;
+
+#[derive(Debug, PartialEq)]
+pub struct Game {
+    players: usize,
+    last_marble: usize,
+}
+
+solver_input!(Game);
+
+fn parse_game(text: &str) -> Option<Game> {
+    lazy_static::lazy_static! {
+        static ref RE: Regex =
+        Regex::new(r"^(\d+) players; last marble is worth (\d+) points$").unwrap();
+    }
+
+    let cap = RE.captures(text)?;
+    Some(Game {
+        players: cap[1].parse().ok().filter(|&players| players > 0)?,
+        last_marble: cap[2].parse().ok()?,
+    })
+}
+
+#[aoc_generator(day9)]
+pub fn input_game(input: &str) -> Result<Game, ParseError> {
+    parse_game(input.trim()).ok_or_else(|| {
+        ParseError::new(
+            9,
+            1,
+            input.trim(),
+            "a game such as 10 players; last marble is worth 1618 points",
+        )
+    })
+}
+
+// The circle is kept in a VecDeque whose back is always the current marble,
+// so every move is a constant number of rotations, pushes and pops
+fn high_score(players: usize, last_marble: usize) -> usize {
+    let mut scores = vec![0; players];
+    let mut circle = VecDeque::with_capacity(last_marble + 1);
+    circle.push_back(0);
+
+    for marble in 1..=last_marble {
+        if marble % 23 == 0 {
+            circle.rotate_right(7);
+            scores[marble % players] += marble + circle.pop_back().unwrap();
+            circle.rotate_left(1);
+        } else {
+            circle.rotate_left(1);
+            circle.push_back(marble);
+        }
+    }
+
+    scores.into_iter().max().unwrap()
+}
+
+#[aoc(day9, part1)]
+pub fn part1(input: &Game) -> usize {
+    high_score(input.players, input.last_marble)
+}
+
+#[aoc(day9, part2)]
+pub fn part2(input: &Game) -> usize {
+    high_score(input.players, input.last_marble * 100)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grok_input() {
+        assert_eq!(
+            input_game("458 players; last marble is worth 71307 points"),
+            Ok(Game {
+                players: 458,
+                last_marble: 71307,
+            })
+        );
+    }
+
+    #[test]
+    fn malformed() {
+        assert_eq!(
+            input_game("0 players; last marble is worth 25 points"),
+            Err(ParseError::new(
+                9,
+                1,
+                "0 players; last marble is worth 25 points",
+                "a game such as 10 players; last marble is worth 1618 points"
+            ))
+        );
+    }
+
+    #[test]
+    fn sample1() {
+        assert_eq!(high_score(9, 25), 32);
+        assert_eq!(
+            part1(&input_game("10 players; last marble is worth 1618 points").unwrap()),
+            8317
+        );
+        assert_eq!(
+            part1(&input_game("13 players; last marble is worth 7999 points").unwrap()),
+            146373
+        );
+        assert_eq!(
+            part1(&input_game("17 players; last marble is worth 1104 points").unwrap()),
+            2764
+        );
+        assert_eq!(
+            part1(&input_game("21 players; last marble is worth 6111 points").unwrap()),
+            54718
+        );
+        assert_eq!(
+            part1(&input_game("30 players; last marble is worth 5807 points").unwrap()),
+            37305
+        );
+    }
+}
@@ -0,0 +1,274 @@
+// Dependency graph of steps that must be finished before other steps can begin //
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fmt;
+use std::iter::FromIterator;
+
+// Steps can be anything ordered, so the same graph handles the single letter
+// steps of the puzzle as well as named build steps
+#[derive(Debug, PartialEq, Clone)]
+pub struct DependencyGraph<T: Ord + Clone> {
+    prerequisites: BTreeMap<T, BTreeSet<T>>,
+    dependents: BTreeMap<T, BTreeSet<T>>,
+}
+
+// Returned when the steps cannot all be ordered. `cycle` lists the steps of
+// one cycle in dependency order, each one needed by the next and the last
+// needed by the first
+#[derive(Debug, PartialEq, Clone)]
+pub struct CycleError<T> {
+    pub cycle: Vec<T>,
+}
+
+impl<T: fmt::Display> fmt::Display for CycleError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "steps form a cycle: ")?;
+        for step in &self.cycle {
+            write!(f, "{} -> ", step)?;
+        }
+        match self.cycle.first() {
+            Some(first) => write!(f, "{}", first),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<T: fmt::Debug + fmt::Display> Error for CycleError<T> {}
+
+impl<T: Ord + Clone> Default for DependencyGraph<T> {
+    fn default() -> Self {
+        DependencyGraph {
+            prerequisites: BTreeMap::new(),
+            dependents: BTreeMap::new(),
+        }
+    }
+}
+
+impl<T: Ord + Clone> DependencyGraph<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_step(&mut self, step: T) {
+        self.prerequisites.entry(step.clone()).or_default();
+        self.dependents.entry(step).or_default();
+    }
+
+    // Records that `needed` must be finished before `step` can begin
+    pub fn add_dependency(&mut self, needed: T, step: T) {
+        self.add_step(needed.clone());
+        self.add_step(step.clone());
+        self.prerequisites
+            .get_mut(&step)
+            .unwrap()
+            .insert(needed.clone());
+        self.dependents.get_mut(&needed).unwrap().insert(step);
+    }
+
+    pub fn steps(&self) -> impl Iterator<Item = &T> {
+        self.prerequisites.keys()
+    }
+
+    pub fn prerequisites_of(&self, step: &T) -> Option<&BTreeSet<T>> {
+        self.prerequisites.get(step)
+    }
+
+    pub fn dependents_of(&self, step: &T) -> Option<&BTreeSet<T>> {
+        self.dependents.get(step)
+    }
+
+    // Kahn's algorithm, always taking the smallest of the steps that are ready
+    pub fn order(&self) -> Result<Vec<T>, CycleError<T>> {
+        let mut waiting_on: BTreeMap<&T, usize> = self
+            .prerequisites
+            .iter()
+            .map(|(step, needed)| (step, needed.len()))
+            .collect();
+        let mut ready: BTreeSet<&T> = waiting_on
+            .iter()
+            .filter(|(_, &count)| count == 0)
+            .map(|(&step, _)| step)
+            .collect();
+        let mut order = Vec::with_capacity(waiting_on.len());
+
+        while let Some(&step) = ready.iter().next() {
+            ready.remove(step);
+            waiting_on.remove(step);
+            order.push(step.clone());
+
+            for dependent in &self.dependents[step] {
+                let count = waiting_on.get_mut(dependent).unwrap();
+                *count -= 1;
+                if *count == 0 {
+                    ready.insert(dependent);
+                }
+            }
+        }
+
+        if waiting_on.is_empty() {
+            Ok(order)
+        } else {
+            let stuck: BTreeSet<&T> = waiting_on.keys().cloned().collect();
+            Err(self.find_cycle(&stuck))
+        }
+    }
+
+    // Every stuck step is waiting on another stuck step, so walking backwards
+    // through prerequisites has to run into a step it has already seen. The
+    // cycle is reported starting from its smallest step
+    fn find_cycle(&self, stuck: &BTreeSet<&T>) -> CycleError<T> {
+        let mut path: Vec<&T> = vec![];
+        let mut step = *stuck.iter().next().unwrap();
+
+        while !path.contains(&step) {
+            path.push(step);
+            step = self.prerequisites[step]
+                .iter()
+                .find(|needed| stuck.contains(needed))
+                .unwrap();
+        }
+
+        let start = path.iter().position(|&seen| seen == step).unwrap();
+        let mut cycle: Vec<T> = path[start..].iter().map(|&step| step.clone()).collect();
+        cycle.reverse();
+        let smallest = (0..cycle.len()).min_by_key(|&i| &cycle[i]).unwrap();
+        cycle.rotate_left(smallest);
+        CycleError { cycle }
+    }
+}
+
+// Both exporters take `notes`, extra text shown under the name of the steps it
+// covers, such as a step's position in the order or when it was worked on
+impl<T: Ord + Clone + fmt::Display> DependencyGraph<T> {
+    // Renders the graph as a Graphviz digraph, with an edge from every step to
+    // each step that needs it
+    pub fn to_dot(&self, notes: &BTreeMap<T, String>) -> String {
+        let quote = |text: &str| text.replace('\\', "\\\\").replace('"', "\\\"");
+
+        let mut dot = String::from("digraph steps {\n");
+        for step in self.steps() {
+            let name = quote(&step.to_string());
+            match notes.get(step) {
+                Some(note) => {
+                    dot += &format!("    \"{}\" [label=\"{}\\n{}\"];\n", name, name, quote(note))
+                }
+                None => dot += &format!("    \"{}\";\n", name),
+            }
+        }
+        for (needed, dependents) in &self.dependents {
+            for step in dependents {
+                dot += &format!(
+                    "    \"{}\" -> \"{}\";\n",
+                    quote(&needed.to_string()),
+                    quote(&step.to_string())
+                );
+            }
+        }
+        dot + "}\n"
+    }
+
+    // Renders the graph as a Mermaid flowchart. Steps get generated node ids,
+    // since their names may not be valid Mermaid identifiers
+    pub fn to_mermaid(&self, notes: &BTreeMap<T, String>) -> String {
+        let quote = |text: &str| text.replace('"', "#quot;");
+        let ids: BTreeMap<&T, usize> = self.steps().zip(0..).collect();
+
+        let mut mermaid = String::from("flowchart TD\n");
+        for (step, id) in &ids {
+            let mut label = quote(&step.to_string());
+            if let Some(note) = notes.get(step) {
+                label += &format!("<br/>{}", quote(note));
+            }
+            mermaid += &format!("    s{}[\"{}\"]\n", id, label);
+        }
+        for (needed, dependents) in &self.dependents {
+            for step in dependents {
+                mermaid += &format!("    s{} --> s{}\n", ids[needed], ids[step]);
+            }
+        }
+        mermaid
+    }
+}
+
+impl<T: Ord + Clone> FromIterator<(T, T)> for DependencyGraph<T> {
+    fn from_iter<I: IntoIterator<Item = (T, T)>>(iter: I) -> Self {
+        let mut graph = DependencyGraph::new();
+        for (needed, step) in iter {
+            graph.add_dependency(needed, step);
+        }
+        graph
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn named_steps() {
+        let mut graph: DependencyGraph<&str> = vec![
+            ("fetch", "compile"),
+            ("configure", "compile"),
+            ("compile", "test"),
+            ("compile", "package"),
+            ("test", "package"),
+        ]
+        .into_iter()
+        .collect();
+        graph.add_step("lint");
+
+        assert_eq!(
+            graph.order(),
+            Ok(vec![
+                "configure",
+                "fetch",
+                "compile",
+                "lint",
+                "test",
+                "package"
+            ])
+        );
+    }
+
+    #[test]
+    fn export() {
+        let graph: DependencyGraph<&str> = vec![("fetch", "build"), ("say \"hi\"", "build")]
+            .into_iter()
+            .collect();
+        let mut notes = BTreeMap::new();
+        notes.insert("build", "last".to_string());
+
+        assert_eq!(
+            graph.to_dot(&notes),
+            "digraph steps {\n\
+             \x20   \"build\" [label=\"build\\nlast\"];\n\
+             \x20   \"fetch\";\n\
+             \x20   \"say \\\"hi\\\"\";\n\
+             \x20   \"fetch\" -> \"build\";\n\
+             \x20   \"say \\\"hi\\\"\" -> \"build\";\n\
+             }\n"
+        );
+        assert_eq!(
+            graph.to_mermaid(&notes),
+            "flowchart TD\n\
+             \x20   s0[\"build<br/>last\"]\n\
+             \x20   s1[\"fetch\"]\n\
+             \x20   s2[\"say #quot;hi#quot;\"]\n\
+             \x20   s1 --> s0\n\
+             \x20   s2 --> s0\n"
+        );
+    }
+
+    #[test]
+    fn cycle() {
+        let graph: DependencyGraph<char> =
+            vec![('A', 'B'), ('B', 'C'), ('C', 'D'), ('D', 'B'), ('D', 'E')]
+                .into_iter()
+                .collect();
+
+        let error = graph.order().unwrap_err();
+        assert_eq!(error.cycle, vec!['B', 'C', 'D']);
+        assert_eq!(error.to_string(), "steps form a cycle: B -> C -> D -> B");
+    }
+}
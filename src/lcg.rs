@@ -0,0 +1,21 @@
+// Small deterministic number generator for tests that compare a solver with
+// a brute force one over many generated inputs //
+
+pub struct Lcg {
+    state: u64,
+}
+
+impl Lcg {
+    pub fn new(seed: u64) -> Lcg {
+        Lcg { state: seed }
+    }
+
+    // The next number in 0..modulo
+    pub fn below(&mut self, modulo: u64) -> u64 {
+        self.state = self
+            .state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (self.state >> 33) % modulo
+    }
+}
@@ -3,7 +3,29 @@ extern crate aoc_runner;
 #[macro_use]
 extern crate aoc_runner_derive;
 
+// aoc-runner hands the generated input to the solvers through `as_ref`, so a
+// generator returning anything other than a Vec or String needs its output
+// type to be `AsRef` of itself
+macro_rules! solver_input {
+    ($input:ty) => {
+        impl AsRef<$input> for $input {
+            fn as_ref(&self) -> &$input {
+                self
+            }
+        }
+    };
+}
+
+pub mod dependency_graph;
+#[cfg(test)]
+mod lcg;
+pub mod no_answer;
+pub mod parse_error;
+
 pub mod day1;
+pub mod day10;
+pub mod day11;
+pub mod day12;
 pub mod day2;
 pub mod day3;
 pub mod day4;
@@ -12,8 +34,5 @@ pub mod day6;
 pub mod day7;
 pub mod day8;
 pub mod day9;
-pub mod day10;
-pub mod day11;
-pub mod day12;
 
-aoc_lib! { year = 2018 }
\ No newline at end of file
+aoc_lib! { year = 2018 }
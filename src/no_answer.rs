@@ -0,0 +1,24 @@
+// Errors for inputs that a search finds no answer in //
+
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct NoAnswer {
+    pub day: u8,
+    pub reason: &'static str,
+}
+
+impl NoAnswer {
+    pub fn new(day: u8, reason: &'static str) -> NoAnswer {
+        NoAnswer { day, reason }
+    }
+}
+
+impl fmt::Display for NoAnswer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "day {}: {}", self.day, self.reason)
+    }
+}
+
+impl Error for NoAnswer {}
@@ -0,0 +1,74 @@
+// Errors for puzzle inputs that do not match the expected format //
+
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ParseError {
+    pub day: u8,
+    pub line: usize,
+    pub text: String,
+    pub expected: &'static str,
+}
+
+impl ParseError {
+    // `line` counts from 1, the way editors show it
+    pub fn new(day: u8, line: usize, text: &str, expected: &'static str) -> ParseError {
+        ParseError {
+            day,
+            line,
+            text: text.to_string(),
+            expected,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "day {}, line {}: expected {}, found {:?}",
+            self.day, self.line, self.expected, self.text
+        )
+    }
+}
+
+impl Error for ParseError {}
+
+// Parses every line of the input with `parse_line`, reporting the first line
+// it rejects
+pub fn parse_lines<T, F>(
+    input: &str,
+    day: u8,
+    expected: &'static str,
+    parse_line: F,
+) -> Result<Vec<T>, ParseError>
+where
+    F: Fn(&str) -> Option<T>,
+{
+    input
+        .lines()
+        .zip(1..)
+        .map(|(text, line)| {
+            parse_line(text).ok_or_else(|| ParseError::new(day, line, text, expected))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn first_bad_line() {
+        let parsed = parse_lines("1\n2\nthree\nfour", 1, "a number", |l| {
+            l.parse::<usize>().ok()
+        });
+
+        assert_eq!(parsed, Err(ParseError::new(1, 3, "three", "a number")));
+        assert_eq!(
+            parsed.unwrap_err().to_string(),
+            "day 1, line 3: expected a number, found \"three\""
+        );
+    }
+}